@@ -1,9 +1,18 @@
+use core::{fmt, str};
 use x264::*;
 
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 #[repr(u32)]
 /// The colorspace of an image, which details how its colors are represented.
+///
+/// The packed RGB colorspaces aren't converted to YUV. Instead, x264 codes
+/// them as 4:4:4 with the G, B and R components as planes, and signals that
+/// in the VUI, so decoders need to support the High 4:4:4 profile. There is
+/// no ordering with the alpha byte first or with red first and padding.
 pub enum Colorspace {
+    /// A single Y plane with no chroma at all, for monochrome video such as
+    /// depth maps.
+    I400 = X264_CSP_I400,
     /// A Y plane followed by 2x2 subsampled U and V planes.
     I420 = X264_CSP_I420,
     /// A Y plane followed by 2x2 subsampled V and U planes.
@@ -32,15 +41,16 @@ pub enum Colorspace {
     I444 = X264_CSP_I444,
     /// A Y plane followed by V and U planes.
     YV24 = X264_CSP_YV24,
-    /// A packed 24-bit BGR plane.
+    /// A packed 24-bit BGR plane, where the first byte is blue.
     BGR  = X264_CSP_BGR,
-    /// A packed 32-bit BGR plane, where the latter byte is padding.
+    /// A packed 32-bit BGR plane, where the first byte is blue and the last
+    /// byte is padding.
     BGRA = X264_CSP_BGRA,
-    /// A packed 24-bit RGB plane.
+    /// A packed 24-bit RGB plane, where the first byte is red.
     RGB  = X264_CSP_RGB,
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+#[derive(Hash, Eq, PartialEq, Copy, Clone)]
 /// The image's colorspace plus some extra encoding options.
 pub struct Encoding {
     raw: i32,
@@ -67,7 +77,121 @@ impl Encoding {
     /// Gets the colorspace of the encoding.
     pub fn colorspace(self) -> Colorspace {
         use core::mem;
-        unsafe { mem::transmute(self.raw as u32 % X264_CSP_MAX) }
+        unsafe { mem::transmute(self.raw as u32 & X264_CSP_MASK) }
+    }
+
+    /// The number of planes that an image in this encoding has.
+    pub fn plane_count(self) -> usize {
+        use self::Colorspace::*;
+
+        match self.colorspace() {
+            I420 | YV12 | I422 | YV16 | I444 | YV24 => 3,
+            NV12 | NV21 | NV16                      => 2,
+            #[cfg(feature = "yuyv")]
+            YUYV | UYVY                             => 1,
+            I400 | V210 | BGR | BGRA | RGB          => 1,
+        }
+    }
+
+    /// How the chroma of this encoding is subsampled and laid out.
+    pub fn subsampling(self) -> Subsampling {
+        use self::Colorspace::*;
+
+        match self.colorspace() {
+            I400               => Subsampling::Monochrome,
+            I420 | YV12        => Subsampling::Yuv420,
+            NV12 | NV21        => Subsampling::SemiPlanar420,
+            I422 | YV16 | V210 => Subsampling::Yuv422,
+            #[cfg(feature = "yuyv")]
+            YUYV | UYVY        => Subsampling::Yuv422,
+            NV16               => Subsampling::SemiPlanar422,
+            I444 | YV24        => Subsampling::Yuv444,
+            BGR | BGRA | RGB   => Subsampling::Rgb,
+        }
+    }
+
+    /// Whether the linked x264 was built with support for this encoding.
+    ///
+    /// x264 can be built for a single chroma format, such as 4:2:0 only,
+    /// and for a single bit depth. With `Modifier::HighDepth`, the encoder
+    /// also needs `Setup::bit_depth` to be more than 8. The bit depth is
+    /// taken from the headers that the bindings were generated from.
+    pub fn is_supported(self) -> bool {
+        let depth = match X264_BIT_DEPTH {
+            0 => true,
+            8 => !self.has(Modifier::HighDepth),
+            _ => self.has(Modifier::HighDepth),
+        };
+
+        let chroma = unsafe { x264_chroma_format } as u32;
+        let format = match self.subsampling() {
+            Subsampling::Monochrome                          => X264_CSP_I400,
+            Subsampling::Yuv420 | Subsampling::SemiPlanar420 => X264_CSP_I420,
+            Subsampling::Yuv422 | Subsampling::SemiPlanar422 => X264_CSP_I422,
+            Subsampling::Yuv444 | Subsampling::Rgb           => X264_CSP_I444,
+        };
+
+        depth && (chroma == 0 || chroma == format)
+    }
+
+    /// Looks up the encoding for a FourCC, as used by capture APIs.
+    ///
+    /// Some common aliases are accepted too, such as `IYUV` for I420 and
+    /// `YUY2` for YUYV. Unknown codes give `None`.
+    pub fn from_fourcc(fourcc: [u8; 4]) -> Option<Self> {
+        use self::Colorspace::*;
+
+        let csp = match &fourcc {
+            b"Y800" | b"GREY" => I400,
+            b"I420" | b"IYUV" => I420,
+            b"YV12"           => YV12,
+            b"NV12"           => NV12,
+            b"NV21"           => NV21,
+            b"I422" | b"Y42B" => I422,
+            b"YV16"           => YV16,
+            b"NV16"           => NV16,
+            #[cfg(feature = "yuyv")]
+            b"YUY2" | b"YUYV" => YUYV,
+            #[cfg(feature = "yuyv")]
+            b"UYVY"           => UYVY,
+            b"v210"           => V210,
+            b"I444" | b"Y444" => I444,
+            b"YV24"           => YV24,
+            b"BGR3"           => BGR,
+            b"BGRA"           => BGRA,
+            b"RGB3"           => RGB,
+            _                 => return None,
+        };
+
+        Some(csp.into())
+    }
+
+    /// The FourCC of the encoding's colorspace.
+    ///
+    /// Modifiers have no FourCC of their own, so they are ignored.
+    pub fn fourcc(self) -> [u8; 4] {
+        use self::Colorspace::*;
+
+        *match self.colorspace() {
+            I400 => b"Y800",
+            I420 => b"I420",
+            YV12 => b"YV12",
+            NV12 => b"NV12",
+            NV21 => b"NV21",
+            I422 => b"I422",
+            YV16 => b"YV16",
+            NV16 => b"NV16",
+            #[cfg(feature = "yuyv")]
+            YUYV => b"YUY2",
+            #[cfg(feature = "yuyv")]
+            UYVY => b"UYVY",
+            V210 => b"v210",
+            I444 => b"I444",
+            YV24 => b"YV24",
+            BGR  => b"BGR3",
+            BGRA => b"BGRA",
+            RGB  => b"RGB3",
+        }
     }
 
     #[doc(hidden)]
@@ -81,12 +205,149 @@ impl Encoding {
     }
 }
 
+impl fmt::Debug for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encoding")
+            .field("colorspace", &self.colorspace())
+            .field("high_depth", &self.has(Modifier::HighDepth))
+            .field("vertical_flip", &self.has(Modifier::VerticalFlip))
+            .finish()
+    }
+}
+
+/// Names the encoding as in config files, like `i420`, followed by any
+/// modifiers, like `i420+high-depth+vflip`.
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.colorspace().name())?;
+
+        for &(name, modifier) in &MODIFIERS {
+            if self.has(modifier) {
+                write!(f, "+{}", name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses what `Display` writes, ignoring case.
+impl str::FromStr for Encoding {
+    type Err = ParseEncodingError;
+
+    fn from_str(s: &str) -> Result<Self, ParseEncodingError> {
+        let mut parts = s.split('+');
+
+        let name = parts.next().unwrap_or("");
+        let mut encoding: Encoding = COLORSPACES.iter()
+            .cloned()
+            .find(|csp| csp.name().eq_ignore_ascii_case(name))
+            .ok_or(ParseEncodingError)?
+            .into();
+
+        for part in parts {
+            let &(_, modifier) = MODIFIERS.iter()
+                .find(|&&(name, _)| name.eq_ignore_ascii_case(part))
+                .ok_or(ParseEncodingError)?;
+            encoding = encoding.add(modifier);
+        }
+
+        Ok(encoding)
+    }
+}
+
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+/// The error from parsing an unknown encoding.
+pub struct ParseEncodingError;
+
+impl fmt::Display for ParseEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown encoding")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ParseEncodingError {}
+
+const MODIFIERS: [(&str, Modifier); 2] = [
+    ("high-depth", Modifier::HighDepth),
+    ("vflip", Modifier::VerticalFlip),
+];
+
+const COLORSPACES: &[Colorspace] = &[
+    Colorspace::I400,
+    Colorspace::I420,
+    Colorspace::YV12,
+    Colorspace::NV12,
+    Colorspace::NV21,
+    Colorspace::I422,
+    Colorspace::YV16,
+    Colorspace::NV16,
+    #[cfg(feature = "yuyv")]
+    Colorspace::YUYV,
+    #[cfg(feature = "yuyv")]
+    Colorspace::UYVY,
+    Colorspace::V210,
+    Colorspace::I444,
+    Colorspace::YV24,
+    Colorspace::BGR,
+    Colorspace::BGRA,
+    Colorspace::RGB,
+];
+
+impl Colorspace {
+    fn name(self) -> &'static str {
+        use self::Colorspace::*;
+
+        match self {
+            I400 => "i400",
+            I420 => "i420",
+            YV12 => "yv12",
+            NV12 => "nv12",
+            NV21 => "nv21",
+            I422 => "i422",
+            YV16 => "yv16",
+            NV16 => "nv16",
+            #[cfg(feature = "yuyv")]
+            YUYV => "yuyv",
+            #[cfg(feature = "yuyv")]
+            UYVY => "uyvy",
+            V210 => "v210",
+            I444 => "i444",
+            YV24 => "yv24",
+            BGR  => "bgr",
+            BGRA => "bgra",
+            RGB  => "rgb",
+        }
+    }
+}
+
 impl From<Colorspace> for Encoding {
     fn from(csp: Colorspace) -> Self {
         Self { raw: csp as i32 }
     }
 }
 
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+/// The chroma subsampling and layout of an encoding.
+pub enum Subsampling {
+    /// There is no chroma, since the image is monochrome.
+    Monochrome,
+    /// Chroma is 2x2 subsampled, in separate planes.
+    Yuv420,
+    /// Chroma is 2x1 subsampled, in separate planes or packed with luma.
+    Yuv422,
+    /// Chroma isn't subsampled at all.
+    Yuv444,
+    /// There is no chroma, since the pixels are RGB.
+    Rgb,
+    /// Chroma is 2x2 subsampled, in a single interleaved plane.
+    SemiPlanar420,
+    /// Chroma is 2x1 subsampled, in a single interleaved plane.
+    SemiPlanar422,
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 #[repr(i32)]
 /// Some extra encoding options.
 pub enum Modifier {
@@ -95,3 +356,106 @@ pub enum Modifier {
     /// Vertically flips the image.
     VerticalFlip = X264_CSP_VFLIP as i32,
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    /// Fails to compile when a colorspace is added, until it is listed.
+    fn listed(csp: Colorspace) -> bool {
+        use self::Colorspace::*;
+
+        match csp {
+            I400 | I420 | YV12 | NV12 | NV21 | I422 | YV16 | NV16 | V210 |
+            I444 | YV24 | BGR | BGRA | RGB => COLORSPACES.contains(&csp),
+            #[cfg(feature = "yuyv")]
+            YUYV | UYVY => COLORSPACES.contains(&csp),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for &csp in COLORSPACES {
+            assert!(listed(csp));
+
+            for mask in 0..1 << MODIFIERS.len() {
+                let mut encoding = Encoding::from(csp);
+                for (i, &(_, modifier)) in MODIFIERS.iter().enumerate() {
+                    if mask & 1 << i != 0 {
+                        encoding = encoding.add(modifier);
+                    }
+                }
+
+                let name = encoding.to_string();
+                assert_eq!(name.parse(), Ok(encoding), "{}", name);
+                assert_eq!(name.to_uppercase().parse(), Ok(encoding));
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_names() {
+        for name in &["", "i421", "+vflip", "i420+", "i420+flip", "i420 "] {
+            assert_eq!(name.parse::<Encoding>(), Err(ParseEncodingError));
+        }
+    }
+
+    #[test]
+    fn fourcc_round_trip() {
+        for &csp in COLORSPACES {
+            let encoding = Encoding::from(csp);
+            let fourcc = encoding.fourcc();
+            assert_eq!(Encoding::from_fourcc(fourcc), Some(encoding));
+
+            let flipped = encoding.add(Modifier::VerticalFlip);
+            assert_eq!(flipped.fourcc(), encoding.fourcc());
+        }
+    }
+
+    #[test]
+    fn fourcc_aliases() {
+        let aliases = [
+            (b"IYUV", Colorspace::I420),
+            (b"Y42B", Colorspace::I422),
+            (b"Y444", Colorspace::I444),
+            (b"GREY", Colorspace::I400),
+            #[cfg(feature = "yuyv")]
+            (b"YUY2", Colorspace::YUYV),
+            #[cfg(feature = "yuyv")]
+            (b"YUYV", Colorspace::YUYV),
+        ];
+        for &(fourcc, csp) in &aliases {
+            assert_eq!(Encoding::from_fourcc(*fourcc), Some(csp.into()));
+        }
+
+        assert_eq!(Encoding::from_fourcc(*b"ABCD"), None);
+        assert_eq!(Encoding::from_fourcc(*b"i420"), None);
+    }
+
+    #[test]
+    fn layouts() {
+        use self::Colorspace::*;
+        use self::Subsampling::*;
+
+        for &csp in COLORSPACES {
+            let (count, subsampling) = match csp {
+                I400        => (1, Monochrome),
+                I420 | YV12 => (3, Yuv420),
+                NV12 | NV21 => (2, SemiPlanar420),
+                I422 | YV16 => (3, Yuv422),
+                NV16        => (2, SemiPlanar422),
+                #[cfg(feature = "yuyv")]
+                YUYV | UYVY => (1, Yuv422),
+                V210        => (1, Yuv422),
+                I444 | YV24 => (3, Yuv444),
+                BGR  | RGB  => (1, Rgb),
+                BGRA        => (1, Rgb),
+            };
+
+            let encoding = Encoding::from(csp).add(Modifier::HighDepth);
+            assert_eq!(encoding.plane_count(), count, "{:?}", csp);
+            assert_eq!(encoding.subsampling(), subsampling, "{:?}", csp);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use core::{fmt, mem, ops};
+use x264::*;
+
+#[derive(Hash, Eq, PartialEq, Copy, Clone)]
+/// A set of the instruction sets that x264 may use, as its `X264_CPU_*`
+/// flags.
+///
+/// Each constant is the x264 flag of the same name, so `AVX2` is
+/// `X264_CPU_AVX2`. The flags of different architectures share bits, so
+/// only the ones for the target architecture are defined.
+pub struct CpuFlags {
+    raw: u32,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CpuFlags {
+    /// MMX.
+    pub const MMX: CpuFlags = CpuFlags { raw: X264_CPU_MMX };
+    /// MMX2, also called MMXEXT.
+    pub const MMX2: CpuFlags = CpuFlags { raw: X264_CPU_MMX2 };
+    /// SSE.
+    pub const SSE: CpuFlags = CpuFlags { raw: X264_CPU_SSE };
+    /// SSE2.
+    pub const SSE2: CpuFlags = CpuFlags { raw: X264_CPU_SSE2 };
+    /// LZCNT.
+    pub const LZCNT: CpuFlags = CpuFlags { raw: X264_CPU_LZCNT };
+    /// SSE3.
+    pub const SSE3: CpuFlags = CpuFlags { raw: X264_CPU_SSE3 };
+    /// SSSE3.
+    pub const SSSE3: CpuFlags = CpuFlags { raw: X264_CPU_SSSE3 };
+    /// SSE4.1.
+    pub const SSE4: CpuFlags = CpuFlags { raw: X264_CPU_SSE4 };
+    /// SSE4.2.
+    pub const SSE42: CpuFlags = CpuFlags { raw: X264_CPU_SSE42 };
+    /// AVX.
+    pub const AVX: CpuFlags = CpuFlags { raw: X264_CPU_AVX };
+    /// XOP.
+    pub const XOP: CpuFlags = CpuFlags { raw: X264_CPU_XOP };
+    /// FMA4.
+    pub const FMA4: CpuFlags = CpuFlags { raw: X264_CPU_FMA4 };
+    /// FMA3.
+    pub const FMA3: CpuFlags = CpuFlags { raw: X264_CPU_FMA3 };
+    /// BMI1.
+    pub const BMI1: CpuFlags = CpuFlags { raw: X264_CPU_BMI1 };
+    /// BMI2.
+    pub const BMI2: CpuFlags = CpuFlags { raw: X264_CPU_BMI2 };
+    /// AVX2.
+    pub const AVX2: CpuFlags = CpuFlags { raw: X264_CPU_AVX2 };
+    /// AVX-512, which can make some CPUs clock down.
+    pub const AVX512: CpuFlags = CpuFlags { raw: X264_CPU_AVX512 };
+}
+
+#[cfg(target_arch = "arm")]
+impl CpuFlags {
+    /// ARMv6.
+    pub const ARMV6: CpuFlags = CpuFlags { raw: X264_CPU_ARMV6 };
+    /// NEON.
+    pub const NEON: CpuFlags = CpuFlags { raw: X264_CPU_NEON };
+}
+
+#[cfg(target_arch = "aarch64")]
+impl CpuFlags {
+    /// ARMv8.
+    pub const ARMV8: CpuFlags = CpuFlags { raw: X264_CPU_ARMV8 };
+    /// NEON.
+    pub const NEON: CpuFlags = CpuFlags { raw: X264_CPU_NEON };
+}
+
+#[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+impl CpuFlags {
+    /// AltiVec.
+    pub const ALTIVEC: CpuFlags = CpuFlags { raw: X264_CPU_ALTIVEC };
+}
+
+impl CpuFlags {
+    /// No flags, so that x264 uses plain C.
+    pub fn empty() -> Self {
+        CpuFlags { raw: 0 }
+    }
+
+    /// The flags that x264 detects on this machine, and uses by default.
+    ///
+    /// Besides instruction sets, these include x264's hints about the CPU,
+    /// such as its cache line size, which have no constants here. They are
+    /// empty if x264 was built without assembly.
+    pub fn detect() -> Self {
+        let mut raw = mem::MaybeUninit::uninit();
+        unsafe { x264_param_default(raw.as_mut_ptr()) };
+        let raw = unsafe { raw.assume_init() };
+        CpuFlags { raw: raw.cpu }
+    }
+
+    /// Adds the given flags.
+    pub fn add(mut self, flags: CpuFlags) -> Self {
+        self.raw |= flags.raw;
+        self
+    }
+
+    /// Removes the given flags.
+    pub fn remove(mut self, flags: CpuFlags) -> Self {
+        self.raw &= !flags.raw;
+        self
+    }
+
+    /// Checks if all of the given flags are set.
+    pub fn has(self, flags: CpuFlags) -> bool {
+        self.raw & flags.raw == flags.raw
+    }
+
+    /// Whether no flags are set.
+    pub fn is_empty(self) -> bool {
+        self.raw == 0
+    }
+
+    #[doc(hidden)]
+    pub fn from_raw(raw: u32) -> Self {
+        CpuFlags { raw }
+    }
+
+    #[doc(hidden)]
+    pub fn into_raw(self) -> u32 {
+        self.raw
+    }
+}
+
+impl ops::BitOr for CpuFlags {
+    type Output = CpuFlags;
+
+    fn bitor(self, rhs: CpuFlags) -> CpuFlags {
+        self.add(rhs)
+    }
+}
+
+impl fmt::Debug for CpuFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CpuFlags({:#x})", self.raw)
+    }
+}
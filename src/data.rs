@@ -1,8 +1,9 @@
 use core::marker::PhantomData;
+use core::ops::Deref;
 use core::slice;
 use x264::*;
-
-//TODO: Iterator over the units.
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 /// The encoded data, to be used in chunks or in its entirety.
 pub struct Data<'a> {
@@ -21,10 +22,32 @@ impl<'a> Data<'a> {
     }
 
     /// The length (in NAL units, **not** in bytes) of this data sequence.
+    ///
+    /// This shadows the length of the byte-slice that `Data` dereferences
+    /// to, so use `entirety().len()` for the number of bytes.
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Whether there are no NAL units in this data sequence.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates through the units, in order.
+    pub fn units<'b>(&'b self) -> Units<'b, 'a> {
+        Units { data: self, next: 0 }
+    }
+
+    /// Iterates through the units, in order, skipping any filler data.
+    ///
+    /// The filler only pads the stream out to a constant bitrate, so it can
+    /// be left out where the transport doesn't need it. Decoders that check
+    /// that the stream conforms to the signaled CBR HRD may reject it then.
+    pub fn units_without_filler<'b>(&'b self) -> WithoutFiller<Units<'b, 'a>> {
+        WithoutFiller { units: self.units() }
+    }
+
     //TODO: Use index trait(s) once IndexMove becomes a thing.
 
     /// The `i`th unit.
@@ -52,6 +75,7 @@ impl<'a> Data<'a> {
                     H => Priority::High,
                     _ => Priority::Highest,
                 },
+            unit_type: NalUnitType::from_raw(nal.i_type),
             payload:
                 unsafe {
                     slice::from_raw_parts(
@@ -83,9 +107,177 @@ impl<'a> Data<'a> {
     }
 }
 
+/// The data is only valid until the next call to the encoder, which the
+/// borrow of the encoder held by `Data` enforces.
+impl<'a> Deref for Data<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.entirety()
+    }
+}
+
+impl<'a> AsRef<[u8]> for Data<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.entirety()
+    }
+}
+
+/// An iterator through the units of some data.
+pub struct Units<'b, 'a: 'b> {
+    data: &'b Data<'a>,
+    next: usize,
+}
+
+impl<'b, 'a> Iterator for Units<'b, 'a> {
+    type Item = Unit<'a>;
+
+    fn next(&mut self) -> Option<Unit<'a>> {
+        if self.next < self.data.len() {
+            self.next += 1;
+            Some(self.data.unit(self.next - 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.data.len() - self.next;
+        (n, Some(n))
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Encoded data that has been copied out of the encoder, so it can be kept
+/// for as long as needed.
+pub struct DataBuf {
+    bytes: Vec<u8>,
+    units: Vec<(Priority, NalUnitType, usize)>,
+}
+
+#[cfg(feature = "alloc")]
+impl DataBuf {
+    /// The length (in NAL units, **not** in bytes) of this data sequence.
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    /// Whether there are no NAL units in this data sequence.
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// Iterates through the units, in order.
+    pub fn units(&self) -> BufUnits {
+        BufUnits { buf: self, next: 0 }
+    }
+
+    /// Iterates through the units, in order, skipping any filler data, as
+    /// with `Data::units_without_filler`.
+    pub fn units_without_filler(&self) -> WithoutFiller<BufUnits> {
+        WithoutFiller { units: self.units() }
+    }
+
+    /// The `i`th unit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out-of-bounds. In order to be within the bounds,
+    /// `i` must be less than `len`.
+    pub fn unit(&self, i: usize) -> Unit {
+        let (priority, unit_type, end) = self.units[i];
+        let start = if i == 0 { 0 } else { self.units[i - 1].2 };
+
+        Unit { priority, unit_type, payload: &self.bytes[start..end] }
+    }
+
+    /// The entire chunk of data, as one big byte-slice.
+    pub fn entirety(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Takes the bytes, without the boundaries between the units.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'b, 'a> From<&'b Data<'a>> for DataBuf {
+    fn from(data: &'b Data<'a>) -> Self {
+        let mut end = 0;
+        let units = data.units().map(|unit| {
+            end += unit.payload.len();
+            (unit.priority, unit.unit_type, end)
+        }).collect();
+
+        DataBuf { bytes: data.entirety().to_vec(), units }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for DataBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<[u8]> for DataBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// An iterator through the units of some owned data.
+pub struct BufUnits<'a> {
+    buf: &'a DataBuf,
+    next: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for BufUnits<'a> {
+    type Item = Unit<'a>;
+
+    fn next(&mut self) -> Option<Unit<'a>> {
+        if self.next < self.buf.len() {
+            self.next += 1;
+            Some(self.buf.unit(self.next - 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.buf.len() - self.next;
+        (n, Some(n))
+    }
+}
+
+/// An iterator through units that skips `NalUnitType::Filler`.
+pub struct WithoutFiller<I> {
+    units: I,
+}
+
+impl<'a, I: Iterator<Item = Unit<'a>>> Iterator for WithoutFiller<I> {
+    type Item = Unit<'a>;
+
+    fn next(&mut self) -> Option<Unit<'a>> {
+        self.units.find(|unit| unit.unit_type != NalUnitType::Filler)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.units.size_hint().1)
+    }
+}
+
 /// A single NAL unit.
 pub struct Unit<'a> {
     priority: Priority,
+    unit_type: NalUnitType,
     payload: &'a [u8]
 }
 
@@ -94,6 +286,20 @@ impl<'a> Unit<'a> {
     pub fn priority(&self) -> Priority {
         self.priority
     }
+
+    /// The `nal_ref_idc` in the unit's header, from 0 to 3, which is the
+    /// unit's `priority` as a number.
+    ///
+    /// Units at 0 can be dropped without breaking the decoding of any other
+    /// frame, so a relay that has to drop data should start with those.
+    pub fn ref_idc(&self) -> u8 {
+        self.priority as u8
+    }
+
+    /// What this unit contains.
+    pub fn unit_type(&self) -> NalUnitType {
+        self.unit_type
+    }
 }
 
 impl<'a> AsRef<[u8]> for Unit<'a> {
@@ -104,14 +310,76 @@ impl<'a> AsRef<[u8]> for Unit<'a> {
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 #[repr(i32)]
-/// The importance of a given unit.
+/// The importance of a given unit, as its `nal_ref_idc`.
 pub enum Priority {
-    /// Not important at all.
+    /// Not important at all, as with B-frames that nothing references, SEI
+    /// and access unit delimiters.
     Disposable = nal_priority_e_NAL_PRIORITY_DISPOSABLE as i32,
-    /// Not very important.
+    /// Not very important, as with referenced B-frames in a strict pyramid.
     Low = nal_priority_e_NAL_PRIORITY_LOW as i32,
-    /// Pretty important.
+    /// Pretty important, as with I- and P-frames, and referenced B-frames in
+    /// a normal pyramid.
     High = nal_priority_e_NAL_PRIORITY_HIGH as i32,
-    /// Extremely important.
+    /// Extremely important, as with IDR frames and parameter sets.
     Highest = nal_priority_e_NAL_PRIORITY_HIGHEST as i32,
 }
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[repr(i32)]
+/// The contents of a given unit.
+pub enum NalUnitType {
+    /// Something that x264 didn't label.
+    Unknown = nal_unit_type_e_NAL_UNKNOWN as i32,
+    /// A slice of a frame that isn't an IDR frame.
+    Slice = nal_unit_type_e_NAL_SLICE as i32,
+    /// The first partition of a slice.
+    SliceDpa = nal_unit_type_e_NAL_SLICE_DPA as i32,
+    /// The second partition of a slice.
+    SliceDpb = nal_unit_type_e_NAL_SLICE_DPB as i32,
+    /// The third partition of a slice.
+    SliceDpc = nal_unit_type_e_NAL_SLICE_DPC as i32,
+    /// A slice of an IDR frame.
+    SliceIdr = nal_unit_type_e_NAL_SLICE_IDR as i32,
+    /// Supplemental enhancement information, such as HRD timing.
+    Sei = nal_unit_type_e_NAL_SEI as i32,
+    /// A sequence parameter set.
+    Sps = nal_unit_type_e_NAL_SPS as i32,
+    /// A picture parameter set.
+    Pps = nal_unit_type_e_NAL_PPS as i32,
+    /// An access unit delimiter.
+    Aud = nal_unit_type_e_NAL_AUD as i32,
+    /// Filler data, used to pad the bitrate.
+    Filler = nal_unit_type_e_NAL_FILLER as i32,
+}
+
+impl NalUnitType {
+    #[doc(hidden)]
+    pub fn from_raw(raw: i32) -> Self {
+        use self::NalUnitType::*;
+
+        const S: i32 = Slice as i32;
+        const A: i32 = SliceDpa as i32;
+        const B: i32 = SliceDpb as i32;
+        const C: i32 = SliceDpc as i32;
+        const I: i32 = SliceIdr as i32;
+        const E: i32 = Sei as i32;
+        const Q: i32 = Sps as i32;
+        const P: i32 = Pps as i32;
+        const D: i32 = Aud as i32;
+        const F: i32 = Filler as i32;
+
+        match raw {
+            S => Slice,
+            A => SliceDpa,
+            B => SliceDpb,
+            C => SliceDpc,
+            I => SliceIdr,
+            E => Sei,
+            Q => Sps,
+            P => Pps,
+            D => Aud,
+            F => Filler,
+            _ => Unknown,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use Picture;
+
+/// Shifts timestamps so that decoding timestamps are never negative, as MP4
+/// and Matroska require.
+///
+/// With B-frames, x264 starts the decoding timestamps below the first
+/// presentation timestamp, by the reorder delay, so they can go negative.
+/// The first picture out of the encoder decides the shift, which is then
+/// applied to both timestamps of every picture, so that their differences
+/// are kept and the decoding timestamps stay monotonic.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DtsAdjuster {
+    offset: Option<i64>,
+}
+
+impl DtsAdjuster {
+    /// Creates an adjuster that hasn't seen any pictures yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The adjusted presentation and decoding timestamps of a picture.
+    ///
+    /// Pictures have to be passed in the order that the encoder output them.
+    pub fn adjust(&mut self, picture: &Picture) -> (i64, i64) {
+        let dts = picture.dts();
+        let offset = *self.offset.get_or_insert(if dts < 0 { -dts } else { 0 });
+        (picture.pts() + offset, dts + offset)
+    }
+
+    /// The shift that is being applied, once the first picture has been seen.
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use {BPyramid, Colorspace, FrameType, Image, Preset, Setup, Tune};
+
+    #[test]
+    fn pyramid_dts_are_non_negative_and_monotonic() {
+        let setup = Setup::preset(Preset::Medium, Tune::None, false, false);
+        let mut encoder = setup
+            .threads(1)
+            .bframes(3)
+            .b_pyramid(BPyramid::Normal)
+            .build(Colorspace::RGB, 64, 64)
+            .unwrap();
+        let mut adjuster = DtsAdjuster::new();
+        let mut pictures = Vec::new();
+
+        for n in 0..30 {
+            let buf: Vec<u8> = (0..64 * 64 * 3)
+                .map(|i| ((i + n * 5) % 251) as u8)
+                .collect();
+            let (data, picture) = encoder
+                .encode(n as i64, Image::rgb(64, 64, &buf))
+                .unwrap();
+            if !data.is_empty() {
+                pictures.push(picture);
+            }
+        }
+        let mut flush = encoder.flush();
+        while let Some(result) = flush.next() {
+            pictures.push(result.unwrap().1);
+        }
+
+        assert_eq!(pictures.len(), 30);
+        assert!(pictures[0].dts() < 0);
+        assert!(pictures.iter().any(|p| p.frame_type() == FrameType::BRef));
+
+        let mut last = None;
+        for picture in &pictures {
+            let (pts, dts) = adjuster.adjust(picture);
+            assert!(0 <= dts && dts <= pts);
+            if let Some(last) = last {
+                assert!(last < dts);
+            }
+            assert_eq!(pts - dts, picture.pts() - picture.dts());
+            last = Some(dts);
+        }
+        assert_eq!(adjuster.offset(), Some(-pictures[0].dts()));
+    }
+}
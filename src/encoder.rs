@@ -1,11 +1,37 @@
-use {Data, Encoding, Error, Image, Picture, Result, Setup};
-use core::{mem, ptr};
+use {Data, Encoding, Error, FrameTypeHint, Image, ImageField, Level};
+use ReconImage;
+use {NalUnitType, Picture, PictureBuilder, RateControl, Result};
+use {EncoderParams, Setup, SpsInfo, ThreadConfig};
+use core::{fmt, mem, ptr};
+#[cfg(feature = "alloc")]
+use {alloc::vec::Vec, core::slice, DataBuf};
+#[cfg(feature = "alloc")]
+use {plane_sizes, Colorspace, FramePacking, Modifier, Plane};
+#[cfg(feature = "std")]
+use std::{io, time::{Duration, Instant}};
+#[cfg(feature = "std")]
+use std::sync::mpsc::{Receiver, Sender};
+#[cfg(feature = "std")]
+use OwnedImage;
 use x264::*;
 
+/// The SEI payload type of a frame packing arrangement.
+#[cfg(feature = "alloc")]
+const SEI_FRAME_PACKING: i32 = 45;
+
 /// Encodes video.
 pub struct Encoder {
     raw: *mut x264_t,
     params: x264_param_t,
+    setup: x264_param_t,
+    pulldown: &'static [i32],
+    recon: Option<x264_image_t>,
+    recon_crc32: bool,
+    #[cfg(feature = "alloc")]
+    pattern: Vec<FrameTypeHint>,
+    #[cfg(feature = "alloc")]
+    packing_repeat: u32,
+    frames: usize,
 }
 
 impl Encoder {
@@ -16,14 +42,79 @@ impl Encoder {
         Setup::default()
     }
 
+    /// Takes ownership of an encoder from `x264_encoder_open`, which is never
+    /// null, so `Setup::build` checks that first.
     #[doc(hidden)]
-    pub unsafe fn from_raw(raw: *mut x264_t) -> Self {
+    pub unsafe fn from_raw_parts(raw: *mut x264_t, setup: x264_param_t)
+        -> Self
+    {
+        debug_assert!(!raw.is_null());
         let mut params = mem::MaybeUninit::uninit();
         x264_encoder_parameters(raw, params.as_mut_ptr());
         Self {
             raw,
             params: params.assume_init(),
+            setup,
+            pulldown: &[],
+            recon: None,
+            recon_crc32: false,
+            #[cfg(feature = "alloc")]
+            pattern: Vec::new(),
+            #[cfg(feature = "alloc")]
+            packing_repeat: 0,
+            frames: 0,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn set_recon_crc32(&mut self, enabled: bool) {
+        self.recon_crc32 = enabled;
+    }
+
+    #[doc(hidden)]
+    pub fn set_pulldown(&mut self, pulldown: &'static [i32]) {
+        self.pulldown = pulldown;
+    }
+
+    #[doc(hidden)]
+    #[cfg(feature = "alloc")]
+    pub fn set_frame_type_pattern(&mut self, pattern: Vec<FrameTypeHint>) {
+        self.pattern = pattern;
+    }
+
+    #[doc(hidden)]
+    #[cfg(feature = "alloc")]
+    pub fn set_frame_packing_repeat(&mut self, period: u32) {
+        self.packing_repeat = period;
+    }
+
+    /// Starts a fresh stream with the same settings, as if the encoder had
+    /// just been built.
+    ///
+    /// This is cheaper than going through `Setup` again for every clip. Any
+    /// delayed frames that haven't been flushed are discarded, and the next
+    /// frame is an IDR frame. On failure, the old encoder is kept.
+    ///
+    /// This is also the only way to reset rate control, such as after a long
+    /// static period, since x264 can't reset it mid-stream. Reconfiguring
+    /// x264 with the same settings does nothing, and with a new bitrate or
+    /// VBV it only rescales the rates, keeping the buffer's fullness and the
+    /// history that ABR steers by.
+    pub fn reopen(&mut self) -> Result<()> {
+        let raw = unsafe { x264_encoder_open(&mut self.setup) };
+
+        if raw.is_null() {
+            return Err(Error::Failed);
+        }
+
+        unsafe {
+            x264_encoder_close(self.raw);
+            x264_encoder_parameters(raw, &mut self.params);
         }
+        self.raw = raw;
+        self.frames = 0;
+        self.recon = None;
+        Ok(())
     }
 
     /// Feeds a frame to the encoder.
@@ -35,12 +126,222 @@ impl Encoder {
     pub fn encode(&mut self, pts: i64, image: Image)
         -> Result<(Data, Picture)>
     {
-        assert_eq!(image.width(), self.width());
-        assert_eq!(image.height(), self.height());
-        assert_eq!(image.encoding(), self.encoding());
+        self.check(&image);
         unsafe { self.encode_unchecked(pts, image) }
     }
 
+    /// Feeds a frame to the encoder, or fails with `Error::ImageMismatch` if
+    /// the image doesn't match the encoder.
+    ///
+    /// This is `encode` for when the images come from somewhere that can't
+    /// be trusted, so a bad one can be rejected without panicking.
+    pub fn try_encode(&mut self, pts: i64, image: Image)
+        -> Result<(Data, Picture)>
+    {
+        let field =
+            if image.width() != self.width() {
+                ImageField::Width
+            } else if image.height() != self.height() {
+                ImageField::Height
+            } else if image.encoding() != self.encoding() {
+                ImageField::Encoding
+            } else {
+                return unsafe { self.encode_unchecked(pts, image) };
+            };
+
+        Err(Error::ImageMismatch { field })
+    }
+
+    /// Feeds a frame to the encoder, and copies the data out.
+    ///
+    /// Unlike with `encode`, the data doesn't borrow the encoder, so it can
+    /// be kept across calls, at the cost of a copy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the image and the encoder
+    /// regarding width, height or colorspace.
+    #[cfg(feature = "alloc")]
+    pub fn encode_owned(&mut self, pts: i64, image: Image)
+        -> Result<(DataBuf, Picture)>
+    {
+        let (data, picture) = self.encode(pts, image)?;
+        Ok((DataBuf::from(&data), picture))
+    }
+
+    /// Feeds a frame to the encoder, and measures how long x264 took with it.
+    ///
+    /// The time is that of the call into x264 alone. With frame threads,
+    /// the call only hands the frame to a thread and collects whichever
+    /// frame is done, so the time isn't what this frame cost to encode. Use
+    /// `Setup::sliced_threads` or a single thread for that.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the image and the encoder
+    /// regarding width, height or colorspace.
+    #[cfg(feature = "std")]
+    pub fn encode_timed(&mut self, pts: i64, image: Image)
+        -> Result<(Data, Picture, Duration)>
+    {
+        self.check(&image);
+
+        let builder = PictureBuilder::new();
+        let mut picture =
+            self.picture(pts, image, X264_QP_AUTO as i32, builder);
+        let start = Instant::now();
+        let (data, picture) = unsafe { self.encode_raw(&mut picture)? };
+
+        Ok((data, picture, start.elapsed()))
+    }
+
+    /// Feeds an 8-bit frame to an encoder with a higher bit depth, widening
+    /// it first.
+    ///
+    /// x264 won't take 8-bit input once it encodes at a higher depth, so the
+    /// encoder has to be built with `Modifier::HighDepth`, and the image is
+    /// the same encoding without it. Each sample is shifted up to the depth
+    /// of `Setup::bit_depth`, into a buffer that lives for the call. The
+    /// linked x264 must support that depth, which `Encoding::is_supported`
+    /// tells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the encoder isn't high depth, if there is a mismatch between
+    /// the image and the encoder regarding width, height or colorspace, or if
+    /// the image is `Colorspace::V210`, which is never 8-bit.
+    #[cfg(feature = "alloc")]
+    pub fn encode_8bit(&mut self, pts: i64, image: Image)
+        -> Result<(Data, Picture)>
+    {
+        let encoding = self.encoding();
+        let (width, height) = (self.width(), self.height());
+
+        assert!(encoding.has(Modifier::HighDepth));
+        assert_eq!(image.width(), width);
+        assert_eq!(image.height(), height);
+        assert_eq!(image.encoding(), encoding.remove(Modifier::HighDepth));
+        assert_ne!(encoding.colorspace(), Colorspace::V210);
+
+        let shift = self.params.i_bitdepth - 8;
+        let sizes = plane_sizes(image.encoding(), width, height);
+        let raw = image.raw();
+
+        let buffers: Vec<Vec<u8>> = (0..sizes.count).map(|i| {
+            let row = sizes.strides[i] as usize;
+            let mut wide = Vec::with_capacity(2 * sizes.bytes(i));
+
+            for y in 0..sizes.rows[i] as usize {
+                let offset = y * raw.i_stride[i] as usize;
+                let narrow = unsafe {
+                    slice::from_raw_parts(raw.plane[i].add(offset), row)
+                };
+
+                for &sample in narrow {
+                    let sample = (sample as u16) << shift;
+                    wide.extend_from_slice(&sample.to_ne_bytes());
+                }
+            }
+
+            wide
+        }).collect();
+
+        let planes: Vec<Plane> = (0..sizes.count).map(|i| Plane {
+            stride: 2 * sizes.strides[i],
+            data: &buffers[i],
+        }).collect();
+
+        // x264 copies the image, so the buffers can go once it returns.
+        unsafe {
+            let image = Image::new_unchecked(encoding, width, height, &planes);
+            self.encode_image(pts, image, X264_QP_AUTO as i32)
+        }
+    }
+
+    /// Feeds a monochrome frame to the encoder, as a luma plane alone.
+    ///
+    /// An encoder built with `Colorspace::I400` takes the plane as is, which
+    /// needs an x264 with 4:0:0 support, as `Encoding::is_supported` tells.
+    /// Any other encoder with separate chroma planes gets neutral chroma
+    /// made up for it, in buffers that live for the call. That spends a few
+    /// more bits, but works with every build of x264. The samples are in the
+    /// encoder's bit depth, so with `Modifier::HighDepth` they are 16 bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the plane is too small for the encoder, or if the encoder
+    /// takes packed pixels, as with RGB.
+    #[cfg(feature = "alloc")]
+    pub fn encode_gray(&mut self, pts: i64, luma: Plane)
+        -> Result<(Data, Picture)>
+    {
+        let encoding = self.encoding();
+        let (width, height) = (self.width(), self.height());
+        let sizes = plane_sizes(encoding, width, height);
+
+        assert!(encoding.colorspace() == Colorspace::I400 || sizes.count > 1);
+
+        let neutral = if encoding.has(Modifier::HighDepth) {
+            (1u16 << (self.params.i_bitdepth - 1)).to_ne_bytes()
+        } else {
+            [0x80, 0x80]
+        };
+
+        let chroma: Vec<Vec<u8>> = (1..sizes.count).map(|i| {
+            neutral.iter().cycle().take(sizes.bytes(i)).cloned().collect()
+        }).collect();
+
+        let mut planes = Vec::with_capacity(sizes.count);
+        planes.push(luma);
+        for (i, data) in chroma.iter().enumerate() {
+            planes.push(Plane { stride: sizes.strides[i + 1], data });
+        }
+
+        let image = Image::new(encoding, width, height, &planes);
+        unsafe { self.encode_image(pts, image, X264_QP_AUTO as i32) }
+    }
+
+    /// Feeds a frame to the encoder, forcing it to be coded at the given QP.
+    ///
+    /// This overrides whatever QP rate control would have chosen for the
+    /// frame, in any rate-control mode, though the bits it costs still count
+    /// towards the bitrate. `Picture::qp` shows which QP ended up being used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the image and the encoder
+    /// regarding width, height or colorspace.
+    pub fn encode_with_qp(&mut self, pts: i64, image: Image, qp: i32)
+        -> Result<(Data, Picture)>
+    {
+        self.check(&image);
+        unsafe { self.encode_image(pts, image, qp + 1) }
+    }
+
+    /// Feeds a frame to the encoder, with the options for it collected in a
+    /// `PictureBuilder`.
+    ///
+    /// A frame type forced by the builder takes precedence over
+    /// `Setup::frame_type_pattern`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the image and the encoder
+    /// regarding width, height or colorspace, or if there is a quant offset
+    /// map of the wrong length.
+    pub fn encode_picture(
+        &mut self,
+        pts: i64,
+        image: Image,
+        builder: PictureBuilder,
+    ) -> Result<(Data, Picture)> {
+        self.check(&image);
+
+        let mut picture =
+            self.picture(pts, image, X264_QP_AUTO as i32, builder);
+        unsafe { self.encode_raw(&mut picture) }
+    }
+
     /// Feeds a frame to the encoder.
     ///
     /// # Unsafety
@@ -50,36 +351,149 @@ impl Encoder {
     pub unsafe fn encode_unchecked(&mut self, pts: i64, image: Image)
         -> Result<(Data, Picture)>
     {
-        let image = image.raw();
+        self.encode_image(pts, image, X264_QP_AUTO as i32)
+    }
+
+    fn check(&self, image: &Image) {
+        assert_eq!(image.width(), self.width());
+        assert_eq!(image.height(), self.height());
+        assert_eq!(image.encoding(), self.encoding());
+    }
+
+    #[cfg(feature = "alloc")]
+    fn next_type(&self) -> FrameTypeHint {
+        if self.pattern.is_empty() {
+            FrameTypeHint::Auto
+        } else {
+            self.pattern[self.frames % self.pattern.len()]
+        }
+    }
 
-        let mut picture = mem::MaybeUninit::uninit();
-        x264_picture_init(picture.as_mut_ptr());
-        let mut picture = picture.assume_init();
+    #[cfg(not(feature = "alloc"))]
+    fn next_type(&self) -> FrameTypeHint {
+        FrameTypeHint::Auto
+    }
+
+    unsafe fn encode_image(&mut self, pts: i64, image: Image, qpplus1: i32)
+        -> Result<(Data, Picture)>
+    {
+        let builder = PictureBuilder::new();
+        let mut picture = self.picture(pts, image, qpplus1, builder);
+        self.encode_raw(&mut picture)
+    }
+
+    fn picture(
+        &mut self,
+        pts: i64,
+        image: Image,
+        qpplus1: i32,
+        builder: PictureBuilder,
+    ) -> x264_picture_t {
+        let mut picture = unsafe {
+            let mut picture = mem::MaybeUninit::uninit();
+            x264_picture_init(picture.as_mut_ptr());
+            picture.assume_init()
+        };
         picture.i_pts = pts;
-        picture.img = image;
+        picture.i_qpplus1 = qpplus1;
+        picture.i_type = self.next_type() as i32;
+        picture.img = image.raw();
+
+        if !self.pulldown.is_empty() {
+            let len = self.pulldown.len();
+            picture.i_pic_struct = self.pulldown[self.frames % len];
+        }
+
+        #[cfg(feature = "alloc")]
+        let builder = match self.packing_sei() {
+            Some((payload, len)) =>
+                builder.sei(SEI_FRAME_PACKING, &payload[..len]),
+            None => builder,
+        };
+        builder.apply(&mut picture, self);
+
+        self.frames += 1;
+        picture
+    }
+
+    #[cfg(feature = "alloc")]
+    fn packing_sei(&self) -> Option<([u8; 8], usize)> {
+        let period = self.packing_repeat as usize;
+        let packing = FramePacking::from_raw(self.params.i_frame_packing)?;
 
+        let due = self.frames > 0 && self.frames.checked_rem(period) == Some(0);
+
+        if due && packing != FramePacking::FrameAlternation {
+            Some(packing.sei_payload(self.frames & 1 == 0))
+        } else {
+            None
+        }
+    }
+
+    unsafe fn encode_raw(&mut self, picture: &mut x264_picture_t)
+        -> Result<(Data, Picture)>
+    {
         let mut len = 0;
         let mut stuff = mem::MaybeUninit::uninit();
         let mut raw = mem::MaybeUninit::uninit();
 
+        // x264 may reuse the last frame's buffer as soon as it is called.
+        self.recon = None;
+
         let err = x264_encoder_encode(
             self.raw,
             stuff.as_mut_ptr(),
             &mut len,
-            &mut picture,
+            picture,
             raw.as_mut_ptr(),
         );
 
         if err < 0 {
-            Err(Error)
+            Err(Error::Failed)
         } else {
+            let crc32 = self.keep_recon(err, raw.as_ptr());
             let data = Data::from_raw_parts(stuff.assume_init(), len as usize);
-            let picture = Picture::from_raw(raw.assume_init());
+            let picture = Picture::from_raw_parts(
+                raw.assume_init(),
+                err as usize,
+                self.params.i_bframe_pyramid != 0,
+                crc32,
+            );
             Ok((data, picture))
         }
     }
 
+    unsafe fn keep_recon(&mut self, size: i32, raw: *const x264_picture_t)
+        -> Option<u32>
+    {
+        if size > 0 && self.params.b_full_recon != 0 {
+            self.recon = Some((*raw).img);
+        }
+
+        if self.recon_crc32 {
+            self.reconstructed().map(|recon| recon.crc32())
+        } else {
+            None
+        }
+    }
+
+    /// The reconstructed frame, as a decoder would see it, of the last frame
+    /// to come out of the encoder.
+    ///
+    /// This needs `Setup::full_recon`, and is `None` without it, or before
+    /// any frame has come out. The planes are x264's own, so they're only
+    /// valid until the next call to the encoder, which the borrow enforces.
+    /// They are in x264's internal layout, as `ReconImage::encoding` says.
+    pub fn reconstructed(&self) -> Option<ReconImage> {
+        self.recon.map(|raw| unsafe {
+            ReconImage::from_raw_parts(raw, self.width(), self.height())
+        })
+    }
+
     /// Gets the video headers, which should be sent first.
+    ///
+    /// They are framed like every other unit, as `Setup::annexb` says, so
+    /// they need no conversion to go at the start of the stream.
     pub fn headers(&mut self) -> Result<Data> {
         let mut len = 0;
         let mut stuff = mem::MaybeUninit::uninit();
@@ -93,12 +507,23 @@ impl Encoder {
         };
 
         if err < 0 {
-            Err(Error)
+            Err(Error::Failed)
         } else {
             Ok(unsafe { Data::from_raw_parts(stuff.assume_init(), len as usize) })
         }
     }
 
+    /// Parses the SPS out of the headers.
+    pub fn sps_info(&mut self) -> Result<SpsInfo> {
+        let prefix = if self.params.b_annexb != 0 { 0 } else { 4 };
+        let headers = self.headers()?;
+
+        headers.units()
+            .find(|unit| unit.unit_type() == NalUnitType::Sps)
+            .and_then(|unit| SpsInfo::parse(&unit.as_ref()[prefix..]))
+            .ok_or(Error::Failed)
+    }
+
     /// Begins flushing the encoder, to handle any delayed frames.
     ///
     /// ```rust
@@ -117,13 +542,102 @@ impl Encoder {
         Flush { encoder: self }
     }
 
+    /// Flushes the encoder, writing the delayed frames' data out as it comes.
+    ///
+    /// This returns once every delayed frame has been written. Failures of
+    /// the encoder come back as `io::ErrorKind::Other`, wrapping an `Error`.
+    #[cfg(feature = "std")]
+    pub fn flush_into<W: io::Write>(self, out: &mut W) -> io::Result<()> {
+        let mut flush = self.flush();
+
+        while let Some(result) = flush.next() {
+            let (data, _) = result.map_err(io::Error::other)?;
+            out.write_all(data.entirety())?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the frames that come through a channel, sending the data for
+    /// each to another, until the first channel closes, and then flushes.
+    ///
+    /// This is meant to run on its own thread, as part of a pipeline. Every
+    /// frame sends one result, which is empty while x264 holds frames back,
+    /// and images that don't match the encoder send `Error::ImageMismatch`.
+    ///
+    /// The headers aren't sent on their own, so unless they are repeated
+    /// before every keyframe, which is the default, send them from `headers`
+    /// beforehand. This returns early if the data channel closes.
+    #[cfg(feature = "std")]
+    pub fn run_channel(
+        mut self,
+        rx: Receiver<(i64, OwnedImage)>,
+        tx: Sender<Result<DataBuf>>,
+    ) {
+        for (pts, image) in rx {
+            let result = self.try_encode(pts, image.as_image())
+                .map(|(data, _)| DataBuf::from(&data));
+
+            if tx.send(result).is_err() {
+                return;
+            }
+        }
+
+        let mut flush = self.flush();
+        while let Some(result) = flush.next() {
+            let result = result.map(|(data, _)| DataBuf::from(&data));
+
+            if tx.send(result).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Encodes a whole clip into one Annex-B byte stream, such as a `.264`
+    /// file.
+    ///
+    /// The headers come first, unless they are already repeated before every
+    /// keyframe, then every frame and then the delayed frames. The entire
+    /// stream is kept in memory, so for long clips, or for streaming, use
+    /// `encode` and `flush` instead and write out the data as it comes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between any image and the encoder
+    /// regarding width, height or colorspace.
+    #[cfg(feature = "alloc")]
+    pub fn encode_stream<'a, I>(mut self, frames: I) -> Result<Vec<u8>>
+    where
+        I: IntoIterator<Item = (i64, Image<'a>)>,
+    {
+        let mut stream = Vec::new();
+
+        if self.params.b_repeat_headers == 0 {
+            stream.extend_from_slice(self.headers()?.entirety());
+        }
+
+        for (pts, image) in frames {
+            let (data, _) = self.encode(pts, image)?;
+            stream.extend_from_slice(data.entirety());
+        }
+
+        let mut flush = self.flush();
+        while let Some(result) = flush.next() {
+            let (data, _) = result?;
+            stream.extend_from_slice(data.entirety());
+        }
+
+        Ok(stream)
+    }
+
     /// If an intra refresh is not in progress, begin one with the next P-frame.
     /// If an intra refresh is in progress, begin one as soon as the current one finishes.
-    /// Requires that b_intra_refresh be set.
+    /// Requires that the encoder was built with `Setup::intra_refresh_period`.
     ///
     /// Useful for interactive streaming where the client can tell the server that packet loss has
     /// occurred.  In this case, keyint can be set to an extremely high value so that intra refreshes
-    /// only occur when calling x264_encoder_intra_refresh.
+    /// only occur when calling x264_encoder_intra_refresh. `Setup::feedback_driven_refresh`
+    /// configures exactly that.
     ///
     /// In multi-pass encoding, if x264_encoder_intra_refresh is called differently in each pass,
     /// behavior is undefined.
@@ -137,10 +651,135 @@ impl Encoder {
     pub fn width(&self) -> i32 { self.params.i_width }
     /// The height required of any input images.
     pub fn height(&self) -> i32 { self.params.i_height }
+    /// The number of macroblocks across each frame, which is the width
+    /// divided by 16, rounded up.
+    pub fn mb_width(&self) -> u32 {
+        ((self.params.i_width + 15) / 16) as u32
+    }
+    /// The number of rows of macroblocks in each frame, which is the height
+    /// divided by 16, rounded up, and then up to an even number when the
+    /// macroblocks are coded in pairs for interlacing.
+    ///
+    /// This times `mb_width` is the length of `PictureBuilder::quant_offsets`.
+    pub fn mb_height(&self) -> u32 {
+        let rows = (self.params.i_height + 15) / 16;
+        let interlaced = self.params.b_interlaced != 0
+            || self.params.b_fake_interlaced != 0;
+        (if interlaced { (rows + 1) & !1 } else { rows }) as u32
+    }
     /// The encoding required of any input images.
     pub fn encoding(&self) -> Encoding {
         unsafe { Encoding::from_raw(self.params.i_csp) }
     }
+
+    /// The alignment, in bytes, that x264 uses for its own frames.
+    ///
+    /// x264 always copies input images into its own padded frames, so input
+    /// with any alignment or stride works, and aligning it won't avoid the
+    /// copy. Planes whose strides and row widths are multiples of this do
+    /// let the copy use SIMD for every row, though.
+    pub fn input_alignment(&self) -> usize {
+        if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            64
+        } else {
+            16
+        }
+    }
+
+    // Effective settings, after presets, tunes and profiles have been applied.
+
+    /// Reads back the settings that x264 is encoding with right now.
+    ///
+    /// The other getters here are read once, when the encoder is opened or
+    /// reopened, whereas this asks x264 again each time. Either way, they
+    /// are what is left after x264 has clamped and overridden the settings,
+    /// such as a bitrate under a VBV maximum that it can't meet.
+    pub fn effective_params(&self) -> EncoderParams {
+        let mut params = mem::MaybeUninit::uninit();
+        unsafe {
+            x264_encoder_parameters(self.raw, params.as_mut_ptr());
+            EncoderParams::from_raw(params.assume_init())
+        }
+    }
+
+
+    /// The rate-control mode the encoder settled on.
+    pub fn rate_control_mode(&self) -> RateControl {
+        RateControl::from_raw(self.params.rc.i_rc_method)
+    }
+    /// The target bitrate, in metric kilobits per second.
+    pub fn bitrate(&self) -> i32 { self.params.rc.i_bitrate }
+    /// The framerate, as a rational number of frames per second.
+    pub fn fps(&self) -> (u32, u32) {
+        (self.params.i_fps_num, self.params.i_fps_den)
+    }
+    /// The number of threads encoding, which x264 picks from the number of
+    /// CPUs when `Setup::threads` is 0.
+    pub fn thread_count(&self) -> u32 { self.params.i_threads as u32 }
+    /// How the threads are split between encoding and lookahead, after x264
+    /// has picked any automatic counts.
+    pub fn thread_config(&self) -> ThreadConfig {
+        ThreadConfig {
+            frame_threads: self.params.i_threads as u32,
+            lookahead_threads: self.params.i_lookahead_threads as u32,
+            sliced: self.params.b_sliced_threads != 0,
+        }
+    }
+    /// The most frames that the encoder holds back before it outputs one,
+    /// which is how many frames of latency it adds.
+    ///
+    /// This comes from the lookahead, the B-frames and the frame threads,
+    /// as `Setup::latency_profile` describes, and so many frames are left to
+    /// drain with `flush` at the end.
+    pub fn estimated_latency_frames(&self) -> u32 {
+        unsafe { x264_encoder_maximum_delayed_frames(self.raw) as u32 }
+    }
+    /// The H.264 level the stream is flagged with.
+    pub fn level(&self) -> Option<Level> {
+        Level::from_idc(self.params.i_level_idc)
+    }
+    /// The size of the decoded picture buffer, in frames, which a decoder
+    /// needs to hold to play the stream back.
+    ///
+    /// This is the larger of the reference frames and the frames B-frames
+    /// need, which is one more than the reorder depth, or four with a
+    /// B-pyramid, capped at the 16 frames that H.264 allows.
+    pub fn dpb_size(&self) -> u32 {
+        let p = &self.params;
+
+        if p.i_keyint_max == 1 {
+            return 0;
+        }
+
+        let reorder = if p.i_bframe_pyramid != 0 { 2 }
+            else if p.i_bframe != 0 { 1 }
+            else { 0 };
+        let pyramid = if p.i_bframe_pyramid != 0 { 4 } else { 1 };
+
+        p.i_frame_reference
+            .max(1 + reorder)
+            .max(pyramid)
+            .max(p.i_dpb_size)
+            .min(16) as u32
+    }
+}
+
+/// x264 keeps no state outside of the encoder, so it can be moved between
+/// threads, just not shared.
+unsafe impl Send for Encoder {}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encoder")
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("encoding", &self.encoding())
+            .field("rate_control", &self.rate_control_mode())
+            .field("bitrate", &self.bitrate())
+            .field("fps", &self.fps())
+            .field("level", &self.level())
+            .finish()
+    }
 }
 
 impl Drop for Encoder {
@@ -150,11 +789,28 @@ impl Drop for Encoder {
 }
 
 /// Iterate through any delayed frames.
+///
+/// The frames come out in decoding order, picking up right after the last
+/// frame that `Encoder::encode` gave back, so their `Picture::dts` keeps
+/// increasing. With B-frames, their `Picture::pts` are out of order, and
+/// sorting the frames from both by it gives every input frame back once, in
+/// display order.
 pub struct Flush {
     encoder: Encoder,
 }
 
 impl Flush {
+    /// The number of delayed frames that are still to come out.
+    pub fn remaining(&self) -> u32 {
+        unsafe { x264_encoder_delayed_frames(self.encoder.raw) as u32 }
+    }
+
+    /// The reconstructed frame of the last frame to come out, as with
+    /// `Encoder::reconstructed`.
+    pub fn reconstructed(&self) -> Option<ReconImage> {
+        self.encoder.reconstructed()
+    }
+
     /// Keeps flushing.
     pub fn next(&mut self) -> Option<Result<(Data, Picture)>> {
         let enc = self.encoder.raw;
@@ -167,6 +823,8 @@ impl Flush {
         let mut stuff = mem::MaybeUninit::uninit();
         let mut raw = mem::MaybeUninit::uninit();
 
+        self.encoder.recon = None;
+
         let err = unsafe {
             x264_encoder_encode(
                 enc,
@@ -177,13 +835,20 @@ impl Flush {
             )
         };
 
-        Some(if err < 0 {
-            Err(Error)
-        } else {
-            Ok(unsafe {(
-                Data::from_raw_parts(stuff.assume_init(), len as usize),
-                Picture::from_raw(raw.assume_init()),
-            )})
-        })
+        if err < 0 {
+            return Some(Err(Error::Failed));
+        }
+
+        let crc32 = unsafe { self.encoder.keep_recon(err, raw.as_ptr()) };
+
+        Some(Ok(unsafe {(
+            Data::from_raw_parts(stuff.assume_init(), len as usize),
+            Picture::from_raw_parts(
+                raw.assume_init(),
+                err as usize,
+                self.encoder.params.i_bframe_pyramid != 0,
+                crc32,
+            ),
+        )}))
     }
 }
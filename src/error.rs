@@ -1,8 +1,46 @@
-use core::result;
+use core::{fmt, result};
 
 #[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
 /// Indicates that something bad happened.
-pub struct Error;
+pub enum Error {
+    /// x264 failed, or rejected the settings it was given.
+    Failed,
+    /// The image given to `Encoder::try_encode` doesn't match the encoder.
+    ImageMismatch {
+        /// What didn't match.
+        field: ImageField,
+    },
+}
+
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+/// A property of an image that has to match the encoder's.
+pub enum ImageField {
+    /// The width.
+    Width,
+    /// The height.
+    Height,
+    /// The encoding, including its modifiers.
+    Encoding,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Failed => f.write_str("x264 encoding error"),
+            Error::ImageMismatch { field } => {
+                let field = match field {
+                    ImageField::Width    => "width",
+                    ImageField::Height   => "height",
+                    ImageField::Encoding => "encoding",
+                };
+                write!(f, "image {} doesn't match the encoder", field)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
 
 /// A specialized Result type for video encoding operations.
 pub type Result<T> = result::Result<T, Error>;
@@ -1,6 +1,10 @@
 use {Colorspace, Encoding, Modifier};
 use core::marker::PhantomData;
-use core::ptr;
+use core::{ptr, slice};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use x264::*;
 
 /// Input image data to be given to the encoder.
@@ -23,39 +27,16 @@ impl<'a> Image<'a> {
         height: i32,
         planes: &[Plane<'a>],
     ) -> Self {
-        //TODO: Get someone who knows what they're doing to verify this.
-
-        use self::Colorspace::*;
-
         let format = format.into();
+        let sizes = plane_sizes(format, width, height);
 
-        let (pc, wm, hm, ws, hs): (_, _, _, &[_], &[_]) =
-            match format.colorspace() {
-                I420 | YV12 => (3, 2, 2, &[2, 1, 1], &[2, 1, 1]),
-                NV12 | NV21 => (2, 2, 2, &[2, 2],    &[2, 1]   ),
-                I422 | YV16 => (3, 2, 1, &[2, 1, 1], &[1, 1, 1]),
-                NV16        => (2, 2, 1, &[2, 2],    &[1, 1]   ),
-                #[cfg(feature = "yuyv")]
-                YUYV | UYVY => (1, 1, 1, &[2],       &[1]      ),
-                V210        => (1, 1, 1, &[4],       &[1]      ),
-                I444 | YV24 => (3, 1, 1, &[1, 1, 1], &[1, 1, 1]),
-                BGR  | RGB  => (1, 1, 1, &[3],       &[1]      ),
-                BGRA        => (1, 1, 1, &[4],       &[1]      ),
-            };
-
-        let (wq, wr) = (width  / wm, width  % wm);
-        let (hq, hr) = (height / hm, height % hm);
-        let depth    = if format.has(Modifier::HighDepth) { 2 } else { 1 };
-
-        // Check that the number of planes matches pc.
-        assert!(planes.len() == pc);
-        // Check that the width and the height are multiples of wm and hm.
-        assert!(wr == 0 && hr == 0);
+        // Check that the number of planes matches.
+        assert!(planes.len() == sizes.count);
         for (i, plane) in planes.iter().enumerate() {
-            // Check that the plane's stride is at least depth * wq * ws[i].
-            assert!(depth * wq * ws[i] <= plane.stride);
-            // Check that there are at least hq * hs[i] rows in the plane.
-            assert!(hq * hs[i] <= plane.data.len() as i32 / plane.stride);
+            // Check that the plane's stride is wide enough for a row.
+            assert!(sizes.strides[i] <= plane.stride);
+            // Check that there are enough rows in the plane.
+            assert!(sizes.rows[i] <= plane.data.len() as i32 / plane.stride);
         }
 
         unsafe {
@@ -112,6 +93,47 @@ impl<'a> Image<'a> {
         Self { raw, width, height, spooky: PhantomData }
     }
 
+    /// Wraps an image that other code filled in, such as a hardware
+    /// decoder, without repacking it.
+    ///
+    /// # Unsafety
+    ///
+    /// The caller must ensure that the colorspace, the plane count and the
+    /// strides describe the planes, that each plane pointer covers as many
+    /// rows as the colorspace needs at this size, and that the planes
+    /// outlive the image, for `'a`, without being written to.
+    pub unsafe fn from_raw_image(
+        raw: x264_image_t,
+        width: i32,
+        height: i32,
+    ) -> Self {
+        Self { raw, width, height, spooky: PhantomData }
+    }
+
+    /// Makes a new planar image from a frame of the `yuv` crate, keeping
+    /// its strides.
+    ///
+    /// The frame doesn't say how its chroma is subsampled, so the format
+    /// has to, and must be one with three planes, like `Colorspace::I420`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the format doesn't have three planes, or if the frame's
+    /// planes are too small for it.
+    #[cfg(feature = "yuv")]
+    pub fn from_yuv_planes<E: Into<Encoding>>(
+        format: E,
+        frame: &yuv::YuvPlanarImage<'a, u8>,
+    ) -> Self {
+        let planes = [
+            Plane { stride: frame.y_stride as i32, data: frame.y_plane },
+            Plane { stride: frame.u_stride as i32, data: frame.u_plane },
+            Plane { stride: frame.v_stride as i32, data: frame.v_plane },
+        ];
+
+        Self::new(format, frame.width as i32, frame.height as i32, &planes)
+    }
+
     // Getters
 
     /// The width of the image.
@@ -123,10 +145,193 @@ impl<'a> Image<'a> {
         unsafe { Encoding::from_raw(self.raw.i_csp) }
     }
 
+    /// The image as x264 takes it, to hand on to other code.
+    ///
+    /// The plane pointers are only valid for as long as the image is.
+    pub fn as_raw_image(&self) -> &x264_image_t { &self.raw }
+
     #[doc(hidden)]
     pub fn raw(&self) -> x264_image_t { self.raw }
 }
 
+/// A frame that x264 reconstructed, which is what a decoder would output.
+pub struct ReconImage<'a> {
+    raw: x264_image_t,
+    width: i32,
+    height: i32,
+    spooky: PhantomData<&'a [u8]>,
+}
+
+impl<'a> ReconImage<'a> {
+    #[doc(hidden)]
+    pub unsafe fn from_raw_parts(
+        raw: x264_image_t,
+        width: i32,
+        height: i32,
+    ) -> Self {
+        Self { raw, width, height, spooky: PhantomData }
+    }
+
+    /// The width of the image.
+    pub fn width(&self) -> i32 { self.width }
+    /// The height of the image.
+    pub fn height(&self) -> i32 { self.height }
+
+    /// The encoding of the image, which is x264's internal one.
+    ///
+    /// That's `Colorspace::NV12` for 4:2:0, `Colorspace::NV16` for 4:2:2,
+    /// `Colorspace::I444` for 4:4:4 and RGB, which is coded as GBR, and
+    /// `Colorspace::I400` for monochrome. It
+    /// has `Modifier::HighDepth` when encoding at more than 8 bits.
+    pub fn encoding(&self) -> Encoding {
+        unsafe { Encoding::from_raw(self.raw.i_csp) }
+    }
+
+    /// The number of planes.
+    pub fn plane_count(&self) -> usize {
+        self.raw.i_plane as usize
+    }
+
+    /// The stride of the `i`th plane, in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` isn't less than `plane_count`.
+    pub fn stride(&self, i: usize) -> i32 {
+        assert!(i < self.plane_count());
+        self.raw.i_stride[i]
+    }
+
+    /// The `i`th plane, from the start of its first row to the end of its
+    /// last, with `stride` bytes between the rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` isn't less than `plane_count`.
+    pub fn plane(&self, i: usize) -> &[u8] {
+        let sizes = plane_sizes(self.encoding(), self.width, self.height);
+        let stride = self.stride(i) as usize;
+        let rows = sizes.rows[i] as usize;
+        let len = stride * (rows - 1) + sizes.strides[i] as usize;
+
+        unsafe { slice::from_raw_parts(self.raw.plane[i], len) }
+    }
+
+    /// The CRC-32 of the pixels, plane by plane and row by row, leaving out
+    /// the padding at the end of each row.
+    ///
+    /// This is the same CRC as zlib's, and is cheap enough to check that
+    /// encodes are reproducible without keeping whole frames around. It is
+    /// only worked out when asked for, or for every frame with
+    /// `Setup::recon_crc32`, which gives it as `Picture::crc32`.
+    pub fn crc32(&self) -> u32 {
+        let sizes = plane_sizes(self.encoding(), self.width, self.height);
+        let mut crc = !0;
+
+        for i in 0..self.plane_count() {
+            let stride = self.stride(i) as usize;
+            let width = sizes.strides[i] as usize;
+            for row in self.plane(i).chunks(stride) {
+                crc = crc32_update(crc, &row[..width]);
+            }
+        }
+
+        !crc
+    }
+}
+
+/// The CRC-32 of every byte, for the reflected polynomial.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { crc >> 1 ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc = crc >> 8 ^ CRC32_TABLE[(crc as u8 ^ byte) as usize];
+    }
+    crc
+}
+
+/// The minimum layout of each plane of an image.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct PlaneSizes {
+    /// The number of planes.
+    pub count: usize,
+    /// The minimum stride of each plane, in bytes.
+    pub strides: [i32; 3],
+    /// The number of rows in each plane.
+    pub rows: [i32; 3],
+}
+
+impl PlaneSizes {
+    /// The minimum number of bytes in the `i`th plane.
+    pub fn bytes(&self, i: usize) -> usize {
+        self.strides[i] as usize * self.rows[i] as usize
+    }
+}
+
+/// Computes how big each plane of an image must be.
+///
+/// # Panics
+///
+/// Panics if the width or the height isn't a multiple of the chroma
+/// subsampling of the encoding.
+pub fn plane_sizes<E: Into<Encoding>>(
+    format: E,
+    width:  i32,
+    height: i32,
+) -> PlaneSizes {
+    //TODO: Get someone who knows what they're doing to verify this.
+
+    use self::Colorspace::*;
+
+    let format = format.into();
+
+    let (wm, hm, ws, hs): (_, _, &[_], &[_]) =
+        match format.colorspace() {
+            I400        => (1, 1, &[1],       &[1]      ),
+            I420 | YV12 => (2, 2, &[2, 1, 1], &[2, 1, 1]),
+            NV12 | NV21 => (2, 2, &[2, 2],    &[2, 1]   ),
+            I422 | YV16 => (2, 1, &[2, 1, 1], &[1, 1, 1]),
+            NV16        => (2, 1, &[2, 2],    &[1, 1]   ),
+            #[cfg(feature = "yuyv")]
+            YUYV | UYVY => (1, 1, &[2],       &[1]      ),
+            V210        => (1, 1, &[4],       &[1]      ),
+            I444 | YV24 => (1, 1, &[1, 1, 1], &[1, 1, 1]),
+            BGR  | RGB  => (1, 1, &[3],       &[1]      ),
+            BGRA        => (1, 1, &[4],       &[1]      ),
+        };
+
+    let (wq, wr) = (width  / wm, width  % wm);
+    let (hq, hr) = (height / hm, height % hm);
+    let depth    = if format.has(Modifier::HighDepth) { 2 } else { 1 };
+
+    // Check that the width and the height are multiples of wm and hm.
+    assert!(wr == 0 && hr == 0);
+
+    let count = format.plane_count();
+    let mut sizes = PlaneSizes { count, strides: [0; 3], rows: [0; 3] };
+    for i in 0..count {
+        sizes.strides[i] = depth * wq * ws[i];
+        sizes.rows[i] = hq * hs[i];
+    }
+    sizes
+}
+
 /// A single plane of an image.
 pub struct Plane<'a> {
     /// The plane's stride (the number of bytes for each row).
@@ -134,3 +339,155 @@ pub struct Plane<'a> {
     /// The plane's pixel data.
     pub data: &'a [u8],
 }
+
+#[cfg(feature = "alloc")]
+/// An image that owns its planes, so it can be sent to another thread, or
+/// allocated once and refilled for every frame.
+pub struct OwnedImage {
+    encoding: Encoding,
+    width: i32,
+    height: i32,
+    planes: Vec<OwnedPlane>,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedImage {
+    /// Allocates an image with the given information, with every byte 0.
+    ///
+    /// Each row is padded out to a multiple of 64 bytes, the widest that
+    /// x264's SIMD reads. x264 copies each frame into its own buffers, so
+    /// only the strides matter, not where the planes start.
+    ///
+    /// # Panics
+    ///
+    /// Panics like `plane_sizes`.
+    pub fn new<E: Into<Encoding>>(format: E, width: i32, height: i32)
+        -> Self
+    {
+        let encoding = format.into();
+        let sizes = plane_sizes(encoding, width, height);
+        let mut planes = Vec::with_capacity(sizes.count);
+
+        for i in 0..sizes.count {
+            let stride = (sizes.strides[i] + 63) & !63;
+            let data = vec![0; stride as usize * sizes.rows[i] as usize];
+            planes.push(OwnedPlane { stride, data });
+        }
+
+        OwnedImage { encoding, width, height, planes }
+    }
+
+    /// Makes a new image out of the given planes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the plane is invalid.
+    pub fn from_planes<E: Into<Encoding>>(
+        format: E,
+        width:  i32,
+        height: i32,
+        planes: Vec<OwnedPlane>,
+    ) -> Self {
+        let image = OwnedImage {
+            encoding: format.into(),
+            width,
+            height,
+            planes,
+        };
+
+        // Check the planes once, the same way `Image::new` does.
+        let borrowed: Vec<Plane> = image.borrowed_planes();
+        Image::new(image.encoding, width, height, &borrowed);
+
+        image
+    }
+
+    /// The number of bytes between the rows of the `i`th plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` isn't less than the number of planes.
+    pub fn stride(&self, i: usize) -> i32 {
+        self.planes[i].stride
+    }
+
+    /// The pixels of the `i`th plane, to be overwritten in place.
+    ///
+    /// The plane can't be resized, so the image stays valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` isn't less than the number of planes.
+    pub fn plane_mut(&mut self, i: usize) -> &mut [u8] {
+        &mut self.planes[i].data
+    }
+
+    /// Borrows the image, to be given to the encoder.
+    pub fn as_image(&self) -> Image {
+        unsafe {
+            Image::new_unchecked(
+                self.encoding,
+                self.width,
+                self.height,
+                &self.borrowed_planes(),
+            )
+        }
+    }
+
+    fn borrowed_planes(&self) -> Vec<Plane> {
+        self.planes.iter()
+            .map(|plane| Plane { stride: plane.stride, data: &plane.data })
+            .collect()
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// A single plane of an `OwnedImage`.
+pub struct OwnedPlane {
+    /// The plane's stride (the number of bytes for each row).
+    pub stride: i32,
+    /// The plane's pixel data.
+    pub data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(!crc32_update(!0, b""), 0);
+    }
+
+    #[test]
+    fn i420_sizes() {
+        let sizes = plane_sizes(Colorspace::I420, 640, 480);
+        assert_eq!(sizes.count, 3);
+        assert_eq!(sizes.strides, [640, 320, 320]);
+        assert_eq!(sizes.rows, [480, 240, 240]);
+        assert_eq!(sizes.bytes(1), 320 * 240);
+    }
+
+    #[test]
+    fn nv12_has_two_planes() {
+        let sizes = plane_sizes(Colorspace::NV12, 640, 480);
+        assert_eq!(sizes.count, 2);
+        assert_eq!(sizes.strides, [640, 640, 0]);
+        assert_eq!(sizes.rows, [480, 240, 0]);
+    }
+
+    #[test]
+    fn high_depth_doubles_strides() {
+        let format = Encoding::from(Colorspace::I420).add(Modifier::HighDepth);
+        let sizes = plane_sizes(format, 640, 480);
+        assert_eq!(sizes.strides, [1280, 640, 640]);
+        assert_eq!(sizes.rows, [480, 240, 240]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn odd_width_panics_on_420() {
+        plane_sizes(Colorspace::I420, 641, 480);
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::vec::Vec;
+use {Data, Picture};
+
+/// Records where the keyframes start in a stream as it gets written, to
+/// build a seek table.
+///
+/// Every piece of output has to go through `record` or `skip`, in the order
+/// it is written, so that the byte offsets line up with the file. The
+/// timestamps are the presentation timestamps that the pictures come out
+/// with, before any shift from `DtsAdjuster`.
+#[derive(Debug, Default, Clone)]
+pub struct KeyframeIndex {
+    position: u64,
+    keyframes: Vec<(i64, u64)>,
+    recovery_points: Vec<(i64, u64)>,
+}
+
+impl KeyframeIndex {
+    /// Creates an empty index, starting at byte 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a frame, which is written at the current position.
+    pub fn record(&mut self, data: &Data, picture: &Picture) {
+        if picture.is_recovery_point() {
+            self.recovery_points.push((picture.pts(), self.position));
+        } else if picture.keyframe() {
+            self.keyframes.push((picture.pts(), self.position));
+        }
+        self.position += data.entirety().len() as u64;
+    }
+
+    /// Moves past bytes that aren't a frame, such as separate headers.
+    pub fn skip(&mut self, bytes: u64) {
+        self.position += bytes;
+    }
+
+    /// The presentation timestamps and byte offsets of the IDR frames, in
+    /// decoding order.
+    ///
+    /// Only these reset the decoder completely, so they are the safe places
+    /// to seek to. They don't include the recovery points of intra refresh
+    /// or of an open GOP, which are in `recovery_points` instead.
+    pub fn keyframes(&self) -> &[(i64, u64)] {
+        &self.keyframes
+    }
+
+    /// The presentation timestamps and byte offsets of the frames that
+    /// `Picture::is_recovery_point`, in decoding order.
+    ///
+    /// Decoding can start at these, but the picture only becomes whole a few
+    /// frames later, as the recovery point SEI says.
+    pub fn recovery_points(&self) -> &[(i64, u64)] {
+        &self.recovery_points
+    }
+
+    /// The last keyframe at or before a presentation timestamp, which is
+    /// where to start decoding to show that frame.
+    pub fn seek(&self, pts: i64) -> Option<(i64, u64)> {
+        self.keyframes.iter().rev().find(|&&(at, _)| at <= pts).cloned()
+    }
+
+    /// The number of bytes recorded so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Colorspace, Image, Preset, Setup, Tune};
+
+    #[test]
+    fn records_idr_offsets() {
+        let setup = Setup::preset(Preset::Ultrafast, Tune::None, false, true);
+        let mut encoder = setup
+            .keyint(5)
+            .build(Colorspace::RGB, 64, 64)
+            .unwrap();
+        let mut index = KeyframeIndex::new();
+        let mut expected = Vec::new();
+        let mut position = 100;
+        index.skip(100);
+
+        for n in 0..12 {
+            let buf: Vec<u8> = (0..64 * 64 * 3)
+                .map(|i| ((i + n * 5) % 251) as u8)
+                .collect();
+            let (data, picture) = encoder
+                .encode(n as i64, Image::rgb(64, 64, &buf))
+                .unwrap();
+            assert_eq!(picture.keyframe(), n % 5 == 0);
+            if picture.keyframe() {
+                expected.push((n as i64, position));
+            }
+            index.record(&data, &picture);
+            position += data.entirety().len() as u64;
+        }
+
+        assert_eq!(index.keyframes(), &expected[..]);
+        assert_eq!(index.keyframes().len(), 3);
+        assert!(index.recovery_points().is_empty());
+        assert_eq!(index.position(), position);
+
+        assert_eq!(index.seek(-1), None);
+        assert_eq!(index.seek(0), Some(expected[0]));
+        assert_eq!(index.seek(4), Some(expected[0]));
+        assert_eq!(index.seek(5), Some(expected[1]));
+        assert_eq!(index.seek(11), Some(expected[2]));
+    }
+}
@@ -1,24 +1,62 @@
 //! An x264 wrapper, so that you can safely encode H.264 video.
+//!
+//! The crate only needs `core`. The `alloc` feature adds the parts that
+//! need to allocate, like `DataBuf`, and the `std` feature also implements
+//! `std::error::Error`. Both are on by default. The `yuv` feature adds
+//! `Image::from_yuv_planes`, to take frames from the `yuv` crate as is, and
+//! the `memmap2` feature adds `RawYuvReader`, to encode raw video files
+//! without copying them, which needs `std` too. The `testsrc` feature adds
+//! the `testsrc` module, with test patterns to encode.
 
 #![no_std]
 #![warn(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 extern crate x264_sys;
+#[cfg(feature = "yuv")]
+extern crate yuv;
+#[cfg(all(feature = "memmap2", feature = "std"))]
+extern crate memmap2;
 
 use x264_sys::x264;
 
 mod colorspace;
+mod cpu;
 mod data;
+mod dts;
 mod encoder;
 mod error;
 mod image;
+#[cfg(feature = "alloc")]
+mod index;
+mod params;
 mod picture;
+#[cfg(all(feature = "memmap2", feature = "std"))]
+mod reader;
 mod setup;
+mod sps;
+#[cfg(feature = "testsrc")]
+pub mod testsrc;
+#[cfg(feature = "std")]
+mod transcoder;
 
 pub use colorspace::*;
+pub use cpu::*;
 pub use data::*;
+pub use dts::*;
 pub use encoder::*;
 pub use error::*;
 pub use image::*;
+#[cfg(feature = "alloc")]
+pub use index::*;
+pub use params::*;
 pub use picture::*;
+#[cfg(all(feature = "memmap2", feature = "std"))]
+pub use reader::*;
 pub use setup::*;
+pub use sps::*;
+#[cfg(feature = "std")]
+pub use transcoder::*;
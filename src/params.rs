@@ -0,0 +1,104 @@
+use core::fmt;
+use x264::*;
+use {Level, RateControl};
+
+/// A snapshot of the settings that x264 is actually encoding with, from
+/// `Encoder::effective_params`.
+///
+/// x264 clamps and overrides some of what it is given, so these can differ
+/// from what went into `Setup`.
+#[derive(Clone, Copy)]
+pub struct EncoderParams {
+    raw: x264_param_t,
+}
+
+impl EncoderParams {
+    #[doc(hidden)]
+    pub fn from_raw(raw: x264_param_t) -> Self {
+        EncoderParams { raw }
+    }
+
+    /// The rate-control mode.
+    pub fn rate_control(&self) -> RateControl {
+        RateControl::from_raw(self.raw.rc.i_rc_method)
+    }
+    /// The target bitrate, in metric kilobits per second.
+    pub fn bitrate(&self) -> i32 { self.raw.rc.i_bitrate }
+    /// The constant rate factor.
+    pub fn crf(&self) -> f32 { self.raw.rc.f_rf_constant }
+    /// The QP for `RateControl::ConstantQp`.
+    pub fn qp(&self) -> i32 { self.raw.rc.i_qp_constant }
+    /// The smallest and largest QPs that rate control may pick.
+    pub fn qp_range(&self) -> (i32, i32) {
+        (self.raw.rc.i_qp_min, self.raw.rc.i_qp_max)
+    }
+    /// The VBV's maximum bitrate and buffer size, in metric kilobits (per
+    /// second), which are 0 without a VBV.
+    pub fn vbv(&self) -> (i32, i32) {
+        (self.raw.rc.i_vbv_max_bitrate, self.raw.rc.i_vbv_buffer_size)
+    }
+    /// The framerate, as a rational number of frames per second.
+    pub fn fps(&self) -> (u32, u32) {
+        (self.raw.i_fps_num, self.raw.i_fps_den)
+    }
+    /// The timebase that timestamps are in, as a rational number of seconds
+    /// per tick.
+    pub fn timebase(&self) -> (u32, u32) {
+        (self.raw.i_timebase_num, self.raw.i_timebase_den)
+    }
+    /// The number of frame threads.
+    pub fn threads(&self) -> u32 { self.raw.i_threads as u32 }
+    /// The smallest and largest keyframe intervals.
+    pub fn keyint(&self) -> (u32, u32) {
+        (self.raw.i_keyint_min as u32, self.raw.i_keyint_max as u32)
+    }
+    /// The largest number of consecutive B-frames.
+    pub fn bframes(&self) -> u32 { self.raw.i_bframe as u32 }
+    /// The number of reference frames.
+    pub fn ref_frames(&self) -> u32 { self.raw.i_frame_reference as u32 }
+    /// Whether the entropy coder is CABAC, or CAVLC otherwise.
+    pub fn cabac(&self) -> bool { self.raw.b_cabac != 0 }
+    /// The table that CABAC starts P- and B-slices from.
+    pub fn cabac_init_idc(&self) -> u8 { self.raw.i_cabac_init_idc as u8 }
+    /// The H.264 level the stream is flagged with.
+    pub fn level(&self) -> Option<Level> {
+        Level::from_idc(self.raw.i_level_idc)
+    }
+
+    #[doc(hidden)]
+    pub fn raw(&self) -> &x264_param_t { &self.raw }
+}
+
+impl fmt::Debug for EncoderParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncoderParams")
+            .field("rate_control", &self.rate_control())
+            .field("bitrate", &self.bitrate())
+            .field("crf", &self.crf())
+            .field("qp", &self.qp())
+            .field("vbv", &self.vbv())
+            .field("fps", &self.fps())
+            .field("timebase", &self.timebase())
+            .field("threads", &self.threads())
+            .field("keyint", &self.keyint())
+            .field("bframes", &self.bframes())
+            .field("ref_frames", &self.ref_frames())
+            .field("cabac", &self.cabac())
+            .field("cabac_init_idc", &self.cabac_init_idc())
+            .field("level", &self.level())
+            .finish()
+    }
+}
+
+/// How the encoder's threads are split up, from `Encoder::thread_config`.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct ThreadConfig {
+    /// The number of threads that encode, each working on a different frame,
+    /// or all on the same frame with `sliced`.
+    pub frame_threads: u32,
+    /// The number of threads that the lookahead analysis is split across.
+    pub lookahead_threads: u32,
+    /// Whether the threads split each frame into slices, rather than each
+    /// taking a frame.
+    pub sliced: bool,
+}
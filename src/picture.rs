@@ -1,8 +1,17 @@
+use Encoder;
+use core::fmt;
 use x264::*;
+#[cfg(feature = "alloc")]
+use alloc::{alloc, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::{ffi::c_void, mem, ptr};
 
 /// Output information about an encoded frame.
 pub struct Picture {
-    raw: x264_picture_t
+    raw: x264_picture_t,
+    size: usize,
+    pyramid: bool,
+    crc32: Option<u32>,
 }
 
 impl Picture {
@@ -11,18 +20,475 @@ impl Picture {
         self.raw.b_keyframe != 0
     }
 
+    /// Whether decoding can start from this frame, even though it isn't an
+    /// IDR frame.
+    ///
+    /// These are the frames that begin an intra refresh sweep, and with an
+    /// open GOP, the I-frames flagged as keyframes. x264 puts a recovery
+    /// point SEI before each, which tells decoders how many frames it takes
+    /// until the picture is whole again, so muxers should mark them as
+    /// random-access points too.
+    pub fn is_recovery_point(&self) -> bool {
+        self.keyframe() && self.raw.i_type != X264_TYPE_IDR as i32
+    }
+
+    /// How the frame was coded.
+    pub fn frame_type(&self) -> FrameType {
+        FrameType::from_raw(self.raw.i_type)
+    }
+
+    /// Whether other frames may be predicted from this one.
+    ///
+    /// Only `FrameType::B` frames aren't kept as references (which the units
+    /// of their slices also mark as `Priority::Disposable`), so those can be
+    /// dropped without breaking the decoding of anything else.
+    pub fn is_reference(&self) -> bool {
+        self.raw.i_type != X264_TYPE_B as i32
+    }
+
+    /// The temporal layer of the frame, where dropping every frame above a
+    /// given layer leaves the rest decodable.
+    ///
+    /// I and P-frames are layer 0. Without a B-pyramid, B-frames are layer 1.
+    /// With one, referenced B-frames are layer 1 and the rest are layer 2,
+    /// since x264 never nests B-frame references any deeper than that.
+    pub fn temporal_id(&self) -> u32 {
+        match self.frame_type() {
+            FrameType::Idr | FrameType::I | FrameType::P => 0,
+            FrameType::BRef => 1,
+            FrameType::B => if self.pyramid { 2 } else { 1 },
+        }
+    }
+
     /// The presentation timestamp.
+    ///
+    /// This is the timestamp that the frame was given with, passed through
+    /// unchanged, so with B-frames the output's timestamps are the input's
+    /// out of order. Muxers should use it with `dts` for each sample.
     pub fn pts(&self) -> i64 {
         self.raw.i_pts
     }
 
     /// The decoding timestamp.
+    ///
+    /// This increases from each frame to the next, in the order that they
+    /// come out of the encoder. With B-frames, it starts out behind the
+    /// first timestamp that went in, so it can be negative.
     pub fn dts(&self) -> i64 {
         self.raw.i_dts
     }
 
+    /// The QP that the frame was coded at.
+    pub fn qp(&self) -> i32 {
+        self.raw.i_qpplus1 - 1
+    }
+
+    /// The value that was passed to `PictureBuilder::opaque` for the frame.
+    pub fn opaque(&self) -> usize {
+        self.raw.opaque as usize
+    }
+
+    /// The size of the encoded frame, in bytes.
+    ///
+    /// This is the sum of the payloads of every unit output with the frame,
+    /// and is known in every rate-control mode. x264 doesn't expose how many
+    /// of those bits were spent on headers versus residual data, outside of
+    /// the stats file written during a first pass.
+    pub fn size_bytes(&self) -> usize {
+        self.size
+    }
+
+    /// The CRC-32 of the reconstructed frame, as `ReconImage::crc32` gives
+    /// it, for checking that encodes are reproducible.
+    ///
+    /// This needs `Setup::recon_crc32`, and is `None` without it.
+    pub fn crc32(&self) -> Option<u32> {
+        self.crc32
+    }
+
+    #[doc(hidden)]
+    pub unsafe fn from_raw_parts(
+        raw: x264_picture_t,
+        size: usize,
+        pyramid: bool,
+        crc32: Option<u32>,
+    ) -> Self {
+        Self { raw, size, pyramid, crc32 }
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// The type of a coded frame.
+pub enum FrameType {
+    /// An I-frame that starts a new GOP, which nothing before it can be
+    /// referenced across.
+    Idr = X264_TYPE_IDR as i32,
+    /// A frame that is coded without reference to any other.
+    I = X264_TYPE_I as i32,
+    /// A frame that is predicted from earlier frames.
+    P = X264_TYPE_P as i32,
+    /// A B-frame that other B-frames are predicted from.
+    BRef = X264_TYPE_BREF as i32,
+    /// A B-frame that nothing is predicted from.
+    B = X264_TYPE_B as i32,
+}
+
+impl FrameType {
+    #[doc(hidden)]
+    pub fn from_raw(raw: i32) -> Self {
+        use self::FrameType::*;
+
+        match raw as u32 {
+            X264_TYPE_IDR  => Idr,
+            X264_TYPE_I    => I,
+            X264_TYPE_P    => P,
+            X264_TYPE_BREF => BRef,
+            _              => B,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// The type to code an input frame as, in place of x264's own decision.
+pub enum FrameTypeHint {
+    /// Let x264 decide.
+    Auto = X264_TYPE_AUTO as i32,
+    /// An IDR frame.
+    Idr = X264_TYPE_IDR as i32,
+    /// An I-frame, which isn't a keyframe.
+    I = X264_TYPE_I as i32,
+    /// A P-frame.
+    P = X264_TYPE_P as i32,
+    /// A B-frame that is kept as a reference.
+    BRef = X264_TYPE_BREF as i32,
+    /// A B-frame.
+    B = X264_TYPE_B as i32,
+    /// An IDR frame, or with an open GOP, an I-frame flagged as a keyframe.
+    Keyframe = X264_TYPE_KEYFRAME as i32,
+}
+
+impl fmt::Debug for Picture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Picture")
+            .field("frame_type", &self.frame_type())
+            .field("pts", &self.pts())
+            .field("dts", &self.dts())
+            .field("keyframe", &self.keyframe())
+            .field("qp", &self.qp())
+            .field("size_bytes", &self.size_bytes())
+            .finish()
+    }
+}
+
+/// Collects the options for a single frame, for `Encoder::encode_picture`.
+pub struct PictureBuilder<'a> {
+    frame_type: FrameTypeHint,
+    qp: Option<i32>,
+    quant_offsets: Option<&'a [f32]>,
+    opaque: usize,
+    #[cfg(feature = "alloc")]
+    sei: Vec<(i32, Vec<u8>)>,
+    #[cfg(feature = "alloc")]
+    roi: Vec<(Rect, f32)>,
+    #[cfg(feature = "alloc")]
+    aq: f32,
+}
+
+impl<'a> PictureBuilder<'a> {
+    /// Creates a new builder, which leaves every decision to the encoder.
+    pub fn new() -> Self {
+        PictureBuilder {
+            frame_type: FrameTypeHint::Auto,
+            qp: None,
+            quant_offsets: None,
+            opaque: 0,
+            #[cfg(feature = "alloc")]
+            sei: Vec::new(),
+            #[cfg(feature = "alloc")]
+            roi: Vec::new(),
+            #[cfg(feature = "alloc")]
+            aq: 0.0,
+        }
+    }
+
+    /// Forces the frame to be coded as the given type, such as an IDR frame.
+    pub fn frame_type(mut self, hint: FrameTypeHint) -> Self {
+        self.frame_type = hint;
+        self
+    }
+
+    /// Forces the frame to be coded at the given QP, as with
+    /// `Encoder::encode_with_qp`.
+    pub fn qp(mut self, qp: i32) -> Self {
+        self.qp = Some(qp);
+        self
+    }
+
+    /// Offsets the QP of each macroblock, in raster order.
+    ///
+    /// There is one offset for each 16x16 macroblock, so the slice has to be
+    /// exactly `Encoder::mb_width` times `Encoder::mb_height` long. The
+    /// offsets are added on top of adaptive quantization, so negative offsets
+    /// spend more bits on a region. x264 ignores them when adaptive
+    /// quantization is off, as it is with `Preset::Ultrafast`.
+    pub fn quant_offsets(mut self, offsets: &'a [f32]) -> Self {
+        self.quant_offsets = Some(offsets);
+        self
+    }
+
+    /// Offsets the QP of every macroblock that a rectangle touches, such as a
+    /// face, so negative deltas spend more bits on it.
+    ///
+    /// The rectangle is in pixels, and is rounded out to whole macroblocks
+    /// and clipped to the frame. Where rectangles overlap, their deltas are
+    /// summed, and they are added on top of `quant_offsets` too, if it is
+    /// set.
+    #[cfg(feature = "alloc")]
+    pub fn roi(mut self, rect: Rect, qp_delta: f32) -> Self {
+        self.roi.push((rect, qp_delta));
+        self
+    }
+
+    /// Strengthens adaptive quantization for this frame alone, such as for a
+    /// flash, or weakens it with a negative amount.
+    ///
+    /// x264 can't change its AQ strength from one frame to the next, so this
+    /// works out the offsets that variance AQ would add at the given
+    /// strength, and adds them on top of the others, as with `roi`. Flat
+    /// macroblocks get more bits and detailed ones fewer. Only luma is
+    /// measured, whereas x264 measures chroma too, so this comes close to a
+    /// change of AQ strength without matching it exactly.
+    ///
+    /// # Panics
+    ///
+    /// Encoding the frame panics unless the encoder takes 8-bit YUV with a
+    /// plane of its own for luma.
+    #[cfg(feature = "alloc")]
+    pub fn aq_strength(mut self, strength: f32) -> Self {
+        self.aq = strength;
+        self
+    }
+
+    /// A value that is passed through to the frame's `Picture`, which is
+    /// handy to match frames up after reordering.
+    pub fn opaque(mut self, opaque: usize) -> Self {
+        self.opaque = opaque;
+        self
+    }
+
+    /// Adds an SEI message of the given payload type, which is written out
+    /// with the frame.
+    ///
+    /// The payload is copied, and is handed over to x264 until the frame is
+    /// output. x264 adds the emulation prevention bytes itself.
+    ///
+    /// This can't add the buffering period or picture timing messages that
+    /// x264 writes itself, since their syntax depends on x264's own state.
+    /// That means there is no way to carry clock timestamps, such as SMPTE
+    /// timecode, in the picture timing SEI, because x264 always leaves them
+    /// out.
+    #[cfg(feature = "alloc")]
+    pub fn sei(mut self, payload_type: i32, payload: &[u8]) -> Self {
+        self.sei.push((payload_type, payload.to_vec()));
+        self
+    }
+
     #[doc(hidden)]
-    pub unsafe fn from_raw(raw: x264_picture_t) -> Self {
-        Self { raw }
+    pub fn apply(self, raw: &mut x264_picture_t, encoder: &Encoder) {
+        let mb_width = encoder.mb_width() as i32;
+        let mb_height = encoder.mb_height() as i32;
+        let mbs = (mb_width * mb_height) as usize;
+
+        if self.frame_type != FrameTypeHint::Auto {
+            raw.i_type = self.frame_type as i32;
+        }
+        raw.opaque = self.opaque as *mut _;
+
+        if let Some(qp) = self.qp {
+            raw.i_qpplus1 = qp + 1;
+        }
+
+        if let Some(offsets) = self.quant_offsets {
+            assert_eq!(offsets.len(), mbs);
+            raw.prop.quant_offsets = offsets.as_ptr() as *mut f32;
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            if !self.roi.is_empty() || self.aq != 0.0 {
+                unsafe {
+                    let offsets = sei_alloc(mem::size_of::<f32>() * mbs)
+                        as *mut f32;
+
+                    for i in 0..mbs {
+                        let base = self.quant_offsets.map_or(0.0, |o| o[i]);
+                        ptr::write(offsets.add(i), base);
+                    }
+
+                    for &(rect, delta) in &self.roi {
+                        let right = rect.x + rect.width;
+                        let bottom = rect.y + rect.height;
+                        let x0 = (rect.x / 16).max(0);
+                        let y0 = (rect.y / 16).max(0);
+                        let x1 = ((right + 15) / 16).min(mb_width);
+                        let y1 = ((bottom + 15) / 16).min(mb_height);
+
+                        for y in y0..y1 {
+                            for x in x0..x1 {
+                                let i = (y * mb_width + x) as usize;
+                                *offsets.add(i) += delta;
+                            }
+                        }
+                    }
+
+                    if self.aq != 0.0 {
+                        aq_offsets(raw, encoder, self.aq, offsets);
+                    }
+
+                    raw.prop.quant_offsets = offsets;
+                    raw.prop.quant_offsets_free = Some(sei_free);
+                }
+            }
+
+            if !self.sei.is_empty() {
+                raw.extra_sei = unsafe { sei_into_raw(self.sei) };
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+/// A rectangle of pixels, such as a region of interest.
+pub struct Rect {
+    /// The column of the left edge.
+    pub x: i32,
+    /// The row of the top edge.
+    pub y: i32,
+    /// The width.
+    pub width: i32,
+    /// The height.
+    pub height: i32,
+}
+
+impl<'a> Default for PictureBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// x264 frees SEI payloads and quant offsets through a callback that only
+// gets the pointer, so each allocation starts with its own size.
+#[cfg(feature = "alloc")]
+const SEI_HEADER: usize = 16;
+
+/// Adds the offsets of x264's variance AQ at the given strength, measured on
+/// luma alone.
+#[cfg(feature = "alloc")]
+unsafe fn aq_offsets(
+    raw: &x264_picture_t,
+    encoder: &Encoder,
+    strength: f32,
+    offsets: *mut f32,
+) {
+    use {Colorspace::*, Encoding, Modifier};
+
+    let encoding = Encoding::from_raw(raw.img.i_csp);
+    let planar = matches!(
+        encoding.colorspace(),
+        I400 | I420 | YV12 | NV12 | NV21 | I422 | YV16 | NV16 | I444 | YV24
+    );
+    assert!(planar && !encoding.has(Modifier::HighDepth));
+
+    let flip = encoding.has(Modifier::VerticalFlip);
+    let stride = raw.img.i_stride[0] as isize;
+    let luma = raw.img.plane[0];
+    let (width, height) = (encoder.width(), encoder.height());
+    let mb_width = encoder.mb_width() as i32;
+    let mb_height = encoder.mb_height() as i32;
+
+    for mb_y in 0..mb_height {
+        for mb_x in 0..mb_width {
+            let (mut sum, mut ssd, mut n) = (0u64, 0u64, 0u64);
+
+            for y in mb_y * 16..(mb_y * 16 + 16).min(height) {
+                let row = if flip { height - 1 - y } else { y };
+                let row = luma.offset(row as isize * stride);
+                for x in mb_x * 16..(mb_x * 16 + 16).min(width) {
+                    let pixel = *row.offset(x as isize) as u64;
+                    sum += pixel;
+                    ssd += pixel * pixel;
+                    n += 1;
+                }
+            }
+
+            // Interlacing can add a row of macroblocks below the picture.
+            if n == 0 {
+                continue;
+            }
+
+            // The AC energy of a whole macroblock, as x264 measures it.
+            let energy = (ssd - sum * sum / n) * 256 / n;
+            let i = (mb_y * mb_width + mb_x) as usize;
+            *offsets.add(i) += strength * (log2(energy.max(1)) - 14.427);
+        }
+    }
+}
+
+/// A log2 that is good to within about 0.01, since `core` doesn't have one.
+#[cfg(feature = "alloc")]
+fn log2(x: u64) -> f32 {
+    let bits = (x as f32).to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127;
+    let fraction = f32::from_bits(bits & 0x007F_FFFF | 0x3F80_0000) - 1.0;
+    exponent as f32 + fraction * (1.3466 - 0.3466 * fraction)
+}
+
+#[cfg(feature = "alloc")]
+unsafe fn sei_alloc(size: usize) -> *mut u8 {
+    let layout = alloc::Layout::from_size_align_unchecked(
+        size + SEI_HEADER,
+        SEI_HEADER
+    );
+
+    let ptr = alloc::alloc(layout);
+    if ptr.is_null() {
+        alloc::handle_alloc_error(layout);
+    }
+
+    *(ptr as *mut usize) = size + SEI_HEADER;
+    ptr.add(SEI_HEADER)
+}
+
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn sei_free(ptr: *mut c_void) {
+    let ptr = (ptr as *mut u8).sub(SEI_HEADER);
+    let size = *(ptr as *mut usize);
+    alloc::dealloc(
+        ptr,
+        alloc::Layout::from_size_align_unchecked(size, SEI_HEADER)
+    );
+}
+
+#[cfg(feature = "alloc")]
+unsafe fn sei_into_raw(sei: Vec<(i32, Vec<u8>)>) -> x264_sei_t {
+    let size = mem::size_of::<x264_sei_payload_t>() * sei.len();
+    let payloads = sei_alloc(size) as *mut x264_sei_payload_t;
+
+    for (i, (payload_type, data)) in sei.iter().enumerate() {
+        let payload = sei_alloc(data.len());
+        ptr::copy_nonoverlapping(data.as_ptr(), payload, data.len());
+        ptr::write(payloads.add(i), x264_sei_payload_t {
+            payload_size: data.len() as i32,
+            payload_type: *payload_type,
+            payload,
+        });
+    }
+
+    x264_sei_t {
+        num_payloads: sei.len() as i32,
+        payloads,
+        sei_free: Some(sei_free),
     }
 }
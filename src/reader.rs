@@ -0,0 +1,111 @@
+use {plane_sizes, Encoding, Image, Plane, PlaneSizes};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Reads frames straight out of a memory-mapped raw video file, such as a
+/// `.yuv` file, without copying them.
+///
+/// The file has to hold the frames back to back, with each plane's rows
+/// packed tightly, as `plane_sizes` lays them out. A partial frame at the
+/// end is ignored.
+///
+/// The images borrow the map, so they can't outlive the reader. If another
+/// process changes the file while it is mapped, the images change too.
+pub struct RawYuvReader {
+    map: Mmap,
+    encoding: Encoding,
+    width: i32,
+    height: i32,
+    sizes: PlaneSizes,
+    frame_size: usize,
+}
+
+impl RawYuvReader {
+    /// Maps the file at `path`, which holds frames of the given encoding and
+    /// dimensions.
+    ///
+    /// # Panics
+    ///
+    /// Panics like `plane_sizes`.
+    pub fn open<P, E>(path: P, format: E, width: i32, height: i32)
+        -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+        E: Into<Encoding>,
+    {
+        let encoding = format.into();
+        let sizes = plane_sizes(encoding, width, height);
+        let frame_size = (0..sizes.count).map(|i| sizes.bytes(i)).sum();
+
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+
+        Ok(RawYuvReader { map, encoding, width, height, sizes, frame_size })
+    }
+
+    /// The number of whole frames in the file.
+    pub fn len(&self) -> usize {
+        self.map.len().checked_div(self.frame_size).unwrap_or(0)
+    }
+
+    /// Whether there isn't a single whole frame in the file.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `i`th frame, or `None` if the file isn't that long.
+    pub fn frame(&self, i: usize) -> Option<Image> {
+        if i >= self.len() {
+            return None;
+        }
+
+        let mut start = i * self.frame_size;
+        let mut planes = [
+            Plane { stride: 0, data: &[] },
+            Plane { stride: 0, data: &[] },
+            Plane { stride: 0, data: &[] },
+        ];
+
+        for (p, plane) in planes[..self.sizes.count].iter_mut().enumerate() {
+            let end = start + self.sizes.bytes(p);
+            plane.stride = self.sizes.strides[p];
+            plane.data = &self.map[start..end];
+            start = end;
+        }
+
+        Some(Image::new(
+            self.encoding,
+            self.width,
+            self.height,
+            &planes[..self.sizes.count],
+        ))
+    }
+
+    /// Iterates through the frames, in order.
+    pub fn frames(&self) -> RawYuvFrames {
+        RawYuvFrames { reader: self, next: 0 }
+    }
+}
+
+/// An iterator through the frames of a `RawYuvReader`.
+pub struct RawYuvFrames<'a> {
+    reader: &'a RawYuvReader,
+    next: usize,
+}
+
+impl<'a> Iterator for RawYuvFrames<'a> {
+    type Item = Image<'a>;
+
+    fn next(&mut self) -> Option<Image<'a>> {
+        let frame = self.reader.frame(self.next)?;
+        self.next += 1;
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.reader.len() - self.next;
+        (n, Some(n))
+    }
+}
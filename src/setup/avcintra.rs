@@ -0,0 +1,12 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// An AVC-Intra class, named after its bitrate at 1080p, in megabits per
+/// second.
+pub enum AvcIntraClass {
+    /// 4:2:0 input, at 1440x1080 or 960x720.
+    Class50 = 50,
+    /// 4:2:2 input, at 1920x1080 or 1280x720.
+    Class100 = 100,
+    /// 4:2:2 input, at 1920x1080 or 1280x720.
+    Class200 = 200,
+}
@@ -0,0 +1,13 @@
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// Whether B-frames can be used as references by other B-frames.
+pub enum BPyramid {
+    /// B-frames are never references.
+    None = X264_B_PYRAMID_NONE as i32,
+    /// Only one B-frame per minigop is a reference, as Blu-ray requires.
+    Strict = X264_B_PYRAMID_STRICT as i32,
+    /// Several B-frames per minigop may be references.
+    Normal = X264_B_PYRAMID_NORMAL as i32,
+}
@@ -0,0 +1,123 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// How the two views of a stereoscopic video are packed into each frame.
+///
+/// The values are the spec's `frame_packing_arrangement_type`. x264 derives
+/// the other fields from it: `quincunx_sampling_flag` is only set for
+/// `Checkerboard`, and `content_interpretation_type` is 1, with the left
+/// view first, for everything but `Mono2d`, where it is 0.
+pub enum FramePacking {
+    /// The views alternate like the squares of a checkerboard.
+    Checkerboard = 0,
+    /// The views alternate column by column.
+    ColumnInterleaved = 1,
+    /// The views alternate row by row.
+    RowInterleaved = 2,
+    /// The views are side by side.
+    SideBySide = 3,
+    /// The views are on top of each other.
+    TopBottom = 4,
+    /// The views alternate frame by frame.
+    FrameAlternation = 5,
+    /// There is only one view, which is 2D.
+    Mono2d = 6,
+    /// The views are tiled, which needs both sides to be multiples of 3.
+    TileFormat = 7,
+}
+
+impl FramePacking {
+    #[doc(hidden)]
+    pub fn from_raw(raw: i32) -> Option<Self> {
+        use self::FramePacking::*;
+
+        Some(match raw {
+            0 => Checkerboard,
+            1 => ColumnInterleaved,
+            2 => RowInterleaved,
+            3 => SideBySide,
+            4 => TopBottom,
+            5 => FrameAlternation,
+            6 => Mono2d,
+            7 => TileFormat,
+            _ => return None,
+        })
+    }
+
+    /// The payload of the frame packing arrangement SEI, bit for bit as x264
+    /// writes it, and its length.
+    ///
+    /// With `FrameAlternation`, x264 flags the even frames as holding the
+    /// first view, so `even` says which this is.
+    #[doc(hidden)]
+    pub fn sei_payload(self, even: bool) -> ([u8; 8], usize) {
+        let kind = self as u64;
+        let quincunx = self == FramePacking::Checkerboard;
+        let alternation = self == FramePacking::FrameAlternation;
+
+        let grid = if quincunx || alternation { 0 } else { 16 };
+        let period = if alternation { (1, 1) } else { (0b010, 3) };
+
+        let fields = [
+            (1, 1), // frame_packing_arrangement_id, as ue(0)
+            (0, 1), // frame_packing_arrangement_cancel_flag
+            (kind, 7),
+            (quincunx as u64, 1),
+            ((self != FramePacking::Mono2d) as u64, 6),
+            (0, 3), // spatial flipping, frame 0 flipped and field views flags
+            ((alternation && even) as u64, 1), // current_frame_is_frame0
+            (0, 2), // frame0_self_contained_flag, frame1_self_contained_flag
+            (0, grid), // grid positions
+            (0, 8), // frame_packing_arrangement_reserved_byte
+            period, // frame_packing_arrangement_repetition_period, as ue
+            (0, 1), // frame_packing_arrangement_extension_flag
+        ];
+
+        let (mut bits, mut len) = (0u64, 0);
+        for &(value, n) in &fields {
+            bits = bits << n | value;
+            len += n;
+        }
+        if len % 8 != 0 {
+            let pad = 8 - len % 8;
+            bits = bits << pad | 1 << (pad - 1);
+            len += pad;
+        }
+
+        let bytes = (bits << (64 - len)).to_be_bytes();
+        (bytes, len as usize / 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::FramePacking::*;
+
+    /// The payloads that x264's `x264_sei_frame_packing_write` gives, after
+    /// taking out the emulation prevention bytes.
+    #[test]
+    fn sei_payload() {
+        let cases: [(FramePacking, bool, &[u8]); 5] = [
+            (Checkerboard, true, &[0x80, 0x41, 0x00, 0x01, 0x20]),
+            (SideBySide, true, &[0x81, 0x81, 0x00, 0x00, 0x00, 0x01, 0x20]),
+            (TopBottom, true, &[0x82, 0x01, 0x00, 0x00, 0x00, 0x01, 0x20]),
+            (FrameAlternation, true, &[0x82, 0x81, 0x10, 0x02]),
+            (FrameAlternation, false, &[0x82, 0x81, 0x00, 0x02]),
+        ];
+
+        for &(packing, even, bytes) in &cases {
+            let (payload, len) = packing.sei_payload(even);
+            assert_eq!(&payload[..len], bytes, "{:?}", packing);
+            assert!(payload[len..].iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn from_raw() {
+        for raw in 0..8 {
+            let packing = FramePacking::from_raw(raw);
+            assert_eq!(packing.map(|p| p as i32), Some(raw));
+        }
+        assert_eq!(FramePacking::from_raw(8), None);
+    }
+}
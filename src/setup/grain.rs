@@ -0,0 +1,12 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+/// How hard `Setup::preserve_grain` works to keep film grain.
+///
+/// Each step keeps more of the grain, and costs more bitrate for it.
+pub enum GrainStrength {
+    /// For light grain, or to keep a little texture in clean sources.
+    Light,
+    /// Close to `Tune::Grain`.
+    Medium,
+    /// For heavy grain, which would otherwise turn into blotches.
+    Strong,
+}
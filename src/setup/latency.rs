@@ -0,0 +1,13 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+/// How many frames the encoder may hold back, traded against compression,
+/// as `Setup::latency_profile` sets it up.
+pub enum LatencyProfile {
+    /// No delay at all, for video calls and screen sharing.
+    UltraLow,
+    /// A short lookahead, for live streaming.
+    Low,
+    /// Around a second of delay, for most encodes.
+    Balanced,
+    /// As much lookahead as helps, for offline encodes.
+    Quality,
+}
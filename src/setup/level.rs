@@ -0,0 +1,213 @@
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// A standard H.264 level, which caps the decoder resources a stream needs.
+pub enum Level {
+    L1   = 10,
+    L1b  = 9,
+    L1_1 = 11,
+    L1_2 = 12,
+    L1_3 = 13,
+    L2   = 20,
+    L2_1 = 21,
+    L2_2 = 22,
+    L3   = 30,
+    L3_1 = 31,
+    L3_2 = 32,
+    L4   = 40,
+    L4_1 = 41,
+    L4_2 = 42,
+    L5   = 50,
+    L5_1 = 51,
+    L5_2 = 52,
+    L6   = 60,
+    L6_1 = 61,
+    L6_2 = 62,
+}
+
+impl Level {
+    /// The `level_idc` that gets written into the SPS.
+    pub fn idc(self) -> i32 {
+        self as i32
+    }
+
+    /// The maximum frame size, in macroblocks.
+    pub fn max_frame_size(self) -> i32 {
+        use self::Level::*;
+
+        match self {
+            L1 | L1b                  => 99,
+            L1_1 | L1_2 | L1_3 | L2   => 396,
+            L2_1                      => 792,
+            L2_2 | L3                 => 1620,
+            L3_1                      => 3600,
+            L3_2                      => 5120,
+            L4 | L4_1                 => 8192,
+            L4_2                      => 8704,
+            L5                        => 22080,
+            L5_1 | L5_2               => 36864,
+            L6 | L6_1 | L6_2          => 139264,
+        }
+    }
+
+    /// The maximum size of the decoded picture buffer, in macroblocks.
+    pub fn max_dpb_size(self) -> i32 {
+        use self::Level::*;
+
+        match self {
+            L1 | L1b                  => 396,
+            L1_1                      => 900,
+            L1_2 | L1_3 | L2          => 2376,
+            L2_1                      => 4752,
+            L2_2 | L3                 => 8100,
+            L3_1                      => 18000,
+            L3_2                      => 20480,
+            L4 | L4_1                 => 32768,
+            L4_2                      => 34816,
+            L5                        => 110400,
+            L5_1 | L5_2               => 184320,
+            L6 | L6_1 | L6_2          => 696320,
+        }
+    }
+
+    /// The most reference frames of the given size that the decoded picture
+    /// buffer can hold, up to the 16 that H.264 allows.
+    pub fn max_refs(self, width: i32, height: i32) -> u32 {
+        let mbs = ((width + 15) / 16) * ((height + 15) / 16);
+        let refs = self.max_dpb_size().checked_div(mbs).unwrap_or(16);
+        refs.clamp(1, 16) as u32
+    }
+
+    /// Whether a frame of the given size fits within this level.
+    ///
+    /// Besides the total area, a level also limits each side of the frame
+    /// to `sqrt(8 * max_frame_size)` macroblocks.
+    pub fn fits(self, width: i32, height: i32) -> bool {
+        let w = (width + 15) / 16;
+        let h = (height + 15) / 16;
+        let max = self.max_frame_size();
+
+        w * h <= max && w * w <= 8 * max && h * h <= 8 * max
+    }
+
+    #[doc(hidden)]
+    pub fn from_idc(idc: i32) -> Option<Self> {
+        use self::Level::*;
+
+        Some(match idc {
+            10 => L1,
+            9  => L1b,
+            11 => L1_1,
+            12 => L1_2,
+            13 => L1_3,
+            20 => L2,
+            21 => L2_1,
+            22 => L2_2,
+            30 => L3,
+            31 => L3_1,
+            32 => L3_2,
+            40 => L4,
+            41 => L4_1,
+            42 => L4_2,
+            50 => L5,
+            51 => L5_1,
+            52 => L5_2,
+            60 => L6,
+            61 => L6_1,
+            62 => L6_2,
+            _  => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Level::*;
+    use {Colorspace, Error, Preset, Setup, Tune};
+
+    /// Table A-1 of the H.264 spec, as the level, its `level_idc`, MaxFS and
+    /// MaxDpbMbs.
+    const TABLE: [(Level, i32, i32, i32); 20] = [
+        (L1,   10,     99,    396),
+        (L1b,   9,     99,    396),
+        (L1_1, 11,    396,    900),
+        (L1_2, 12,    396,   2376),
+        (L1_3, 13,    396,   2376),
+        (L2,   20,    396,   2376),
+        (L2_1, 21,    792,   4752),
+        (L2_2, 22,   1620,   8100),
+        (L3,   30,   1620,   8100),
+        (L3_1, 31,   3600,  18000),
+        (L3_2, 32,   5120,  20480),
+        (L4,   40,   8192,  32768),
+        (L4_1, 41,   8192,  32768),
+        (L4_2, 42,   8704,  34816),
+        (L5,   50,  22080, 110400),
+        (L5_1, 51,  36864, 184320),
+        (L5_2, 52,  36864, 184320),
+        (L6,   60, 139264, 696320),
+        (L6_1, 61, 139264, 696320),
+        (L6_2, 62, 139264, 696320),
+    ];
+
+    #[test]
+    fn table() {
+        for &(level, idc, frame_size, dpb_size) in &TABLE {
+            assert_eq!(level.idc(), idc);
+            assert_eq!(Level::from_idc(idc), Some(level));
+            assert_eq!(level.max_frame_size(), frame_size, "{:?}", level);
+            assert_eq!(level.max_dpb_size(), dpb_size, "{:?}", level);
+        }
+
+        assert_eq!(Level::from_idc(0), None);
+        assert_eq!(Level::from_idc(14), None);
+    }
+
+    #[test]
+    fn fits() {
+        let cases = [
+            (L1,   176,  144,  true),
+            (L1,   192,  144,  false),
+            (L3,   720,  576,  true),
+            (L3,   1920, 1080, false),
+            (L3_1, 1280, 720,  true),
+            (L3_1, 1920, 1080, false),
+            (L4,   1920, 1080, true),
+            (L4,   2048, 1024, true),
+            (L4,   2048, 1040, false),
+            (L4_2, 2048, 1088, true),
+            (L5_1, 4096, 2304, true),
+            // As wide as a side can be, and one macroblock too wide.
+            (L4,   4096, 512,  true),
+            (L4,   4112, 496,  false),
+        ];
+
+        for &(level, width, height, fits) in &cases {
+            assert_eq!(level.fits(width, height), fits,
+                "{}x{} in {:?}", width, height, level);
+        }
+    }
+
+    #[test]
+    fn max_refs() {
+        assert_eq!(L4.max_refs(1920, 1080), 4);
+        assert_eq!(L4_1.max_refs(1280, 720), 9);
+        assert_eq!(L5_1.max_refs(3840, 2160), 5);
+        assert_eq!(L6.max_refs(320, 240), 16);
+        assert_eq!(L1.max_refs(1920, 1080), 1);
+        assert_eq!(L4.max_refs(0, 0), 16);
+    }
+
+    #[test]
+    fn build_fails_above_the_level() {
+        let setup = Setup::preset(Preset::Ultrafast, Tune::None, false, true)
+            .level(L3);
+        let result = setup.build(Colorspace::I420, 1920, 1080);
+        assert_eq!(result.err(), Some(Error::Failed));
+
+        let setup = Setup::preset(Preset::Ultrafast, Tune::None, false, true)
+            .level(L4);
+        assert!(setup.build(Colorspace::I420, 1920, 1080).is_ok());
+    }
+}
@@ -1,16 +1,50 @@
-use {Encoder, Encoding, Error, Result};
-use core::mem;
+use {CpuFlags, Encoder, Encoding, Error, Result, Subsampling};
+use core::{fmt, mem};
+#[cfg(feature = "alloc")]
+use {alloc::vec::Vec, FrameTypeHint};
 use x264::*;
 
+mod avcintra;
+mod bframes;
+mod framepacking;
+mod grain;
+mod latency;
+mod level;
 mod preset;
+mod ratecontrol;
+mod telecine;
 mod tune;
+mod vui;
+mod warning;
+mod weightp;
 
+pub use self::avcintra::*;
+pub use self::bframes::*;
+pub use self::framepacking::*;
+pub use self::grain::*;
+pub use self::latency::*;
+pub use self::level::*;
 pub use self::preset::*;
+pub use self::ratecontrol::*;
+pub use self::telecine::*;
 pub use self::tune::*;
+pub use self::vui::*;
+pub use self::warning::*;
+pub use self::weightp::*;
 
 /// Builds a new encoder.
 pub struct Setup {
     raw: x264_param_t,
+    psy: (f32, f32),
+    telecine: Telecine,
+    refs_for_level: bool,
+    auto_subme: bool,
+    auto_chroma_qp: bool,
+    recon_crc32: bool,
+    #[cfg(feature = "alloc")]
+    pattern: Vec<FrameTypeHint>,
+    #[cfg(feature = "alloc")]
+    packing_repeat: u32,
 }
 
 impl Setup {
@@ -32,7 +66,21 @@ impl Setup {
             )
         });
 
-        Self { raw: unsafe { raw.assume_init() } }
+        let raw = unsafe { raw.assume_init() };
+
+        Self {
+            raw,
+            psy: (raw.analyse.f_psy_rd, raw.analyse.f_psy_trellis),
+            telecine: Telecine::None,
+            refs_for_level: false,
+            auto_subme: false,
+            auto_chroma_qp: false,
+            recon_crc32: false,
+            #[cfg(feature = "alloc")]
+            pattern: Vec::new(),
+            #[cfg(feature = "alloc")]
+            packing_repeat: 0,
+        }
     }
 
     /// Makes the first pass faster.
@@ -41,6 +89,247 @@ impl Setup {
         self
     }
 
+    /// The number of threads to encode with, where 0 picks automatically.
+    ///
+    /// By default, each thread encodes a different frame, which is fast but
+    /// adds `threads - 1` frames of delay that have to be drained with
+    /// `Encoder::flush`. See `sliced_threads` for the alternative.
+    pub fn threads(mut self, threads: u32) -> Self {
+        self.raw.i_threads = threads as i32;
+        self
+    }
+
+    /// Splits each frame into slices, so that the threads can work on the
+    /// same frame together.
+    ///
+    /// This trades some throughput and compression for latency, since there
+    /// is no delay from threading at all.
+    pub fn sliced_threads(mut self, sliced: bool) -> Self {
+        self.raw.b_sliced_threads = if sliced { 1 } else { 0 };
+        self
+    }
+
+    /// The number of threads used for lookahead analysis, where 0 picks
+    /// automatically.
+    ///
+    /// When picked automatically, this is the same as `threads` with sliced
+    /// threads, and a fraction of it otherwise.
+    ///
+    /// The automatic split gives lookahead between a twelfth and all of the
+    /// frame threads, more the slower its settings are compared to the main
+    /// encode, as in a fast first pass. It also keeps each lookahead thread
+    /// to at least 128 rows of pixels, since more threads make lookahead
+    /// less accurate. x264 can't pin threads to CPUs, so this split is all
+    /// there is to tune.
+    ///
+    /// The automatic split is the best place to start, even with many cores.
+    /// Raise it by hand only if the frame threads are seen waiting on the
+    /// lookahead, and check what x264 settled on with
+    /// `Encoder::thread_config`.
+    pub fn lookahead_threads(mut self, threads: u32) -> Self {
+        self.raw.i_lookahead_threads = threads as i32;
+        self
+    }
+
+    /// Pins the threading down, so that benchmarks are fair and repeatable.
+    ///
+    /// This uses the given number of sliced threads, so threading adds no
+    /// frame delay, does lookahead on a single thread, and makes the output
+    /// identical from run to run and across CPUs. That costs speed and
+    /// compression, so this is not meant for production. x264 gives each
+    /// thread at least 4 rows of macroblocks, so small frames use fewer
+    /// threads, as `Encoder::thread_count` shows.
+    pub fn benchmark_mode(mut self, threads: u32) -> Self {
+        self.raw.i_threads = threads as i32;
+        self.raw.b_sliced_threads = 1;
+        self.raw.i_lookahead_threads = 1;
+        self.raw.i_sync_lookahead = 0;
+        self.raw.b_deterministic = 1;
+        self.raw.b_cpu_independent = 1;
+        self
+    }
+
+    /// The instruction sets that x264 may use, which are all of
+    /// `CpuFlags::detect` by default.
+    ///
+    /// This is for turning some off, as in
+    /// `CpuFlags::detect().remove(CpuFlags::AVX512)`. Flags that weren't
+    /// detected are dropped, since x264 would crash using them.
+    pub fn cpu_flags(mut self, flags: CpuFlags) -> Self {
+        let detected = CpuFlags::detect().into_raw();
+        self.raw.cpu = flags.into_raw() & detected;
+        self
+    }
+
+    /// Replaces keyframes with a column of intra blocks that sweeps across the
+    /// picture over the given number of frames.
+    ///
+    /// This spreads the cost of a keyframe over many frames, which evens out
+    /// the bitrate for streaming. The period is the keyframe interval, since
+    /// a sweep takes the place of a keyframe. When enabled, x264 limits the
+    /// encoder to one reference frame and a closed GOP.
+    ///
+    /// This is required by `Encoder::intra_refresh`.
+    pub fn intra_refresh_period(mut self, frames: u32) -> Self {
+        self.raw.b_intra_refresh = 1;
+        self.raw.i_keyint_max = frames as i32;
+        self
+    }
+
+    /// Sets up intra refresh so that it only happens on demand.
+    ///
+    /// There are no keyframes after the first one, not even at scene cuts,
+    /// so a refresh only begins when `Encoder::intra_refresh` is called, for
+    /// example after the client reports packet loss. The GOP is closed.
+    pub fn feedback_driven_refresh(mut self) -> Self {
+        self.raw.b_intra_refresh = 1;
+        self.raw.i_keyint_max = X264_KEYINT_MAX_INFINITE as i32;
+        self.raw.i_scenecut_threshold = 0;
+        self.raw.b_open_gop = 0;
+        self
+    }
+
+    /// The maximum interval between keyframes, in frames.
+    ///
+    /// A keyframe is forced once this many frames have passed since the last
+    /// one, scene cut or not.
+    pub fn keyint(mut self, frames: u32) -> Self {
+        self.raw.i_keyint_max = frames as i32;
+        self
+    }
+
+    /// The minimum interval between keyframes, in frames, which stops scene
+    /// cuts in quick succession from clustering keyframes.
+    ///
+    /// A scene cut closer than this to the last keyframe is still coded as
+    /// an I-frame, but not as a keyframe, so it can't be seeked to. Past the
+    /// minimum, a scene cut becomes an IDR frame, or with an open GOP, a
+    /// plain I-frame that is flagged as a keyframe. The default of 0 picks
+    /// `keyint / 10` or one second's worth of frames, whichever is fewer, and
+    /// x264 clamps the value to at most `keyint / 2 + 1`.
+    pub fn keyint_min(mut self, frames: u32) -> Self {
+        self.raw.i_keyint_min = frames as i32;
+        self
+    }
+
+    /// How eagerly scene cuts are detected and given an I-frame, from 0,
+    /// which turns detection off, up to 100. The default is 40.
+    pub fn scenecut(mut self, threshold: u32) -> Self {
+        self.raw.i_scenecut_threshold = threshold as i32;
+        self
+    }
+
+    /// The number of frames that each frame can reference, which x264 clamps
+    /// to `1..=16`.
+    ///
+    /// x264 has no separate count for each slice type. This sets the size of
+    /// the decoded picture buffer, unless B-frames need a larger one, which
+    /// `Encoder::dpb_size` reports. x264 only warns if that exceeds the
+    /// level's limit, so pick a count with the level in mind, or use
+    /// `max_refs_for_level`.
+    pub fn ref_frames(mut self, frames: u32) -> Self {
+        self.raw.i_frame_reference = frames as i32;
+        self
+    }
+
+    /// Uses as many reference frames as the pinned level allows at the size
+    /// that the encoder is built for, instead of the preset's count.
+    ///
+    /// The count is the level's `Level::max_refs`, worked out in `build`.
+    /// This does nothing without `level`, and B-frames may still need a
+    /// larger buffer than the level allows, which x264 warns about.
+    pub fn max_refs_for_level(mut self) -> Self {
+        self.refs_for_level = true;
+        self
+    }
+
+    /// Forces a decoded picture buffer larger than the reference frames and
+    /// B-frames would need, in frames.
+    ///
+    /// This is useful to keep the buffer size fixed, such as when switching
+    /// streams without reinitializing the decoder.
+    pub fn dpb_size(mut self, frames: u32) -> Self {
+        self.raw.i_dpb_size = frames as i32;
+        self
+    }
+
+    /// The maximum number of consecutive B-frames, where 0 disables them.
+    pub fn bframes(mut self, frames: u32) -> Self {
+        self.raw.i_bframe = frames as i32;
+        self
+    }
+
+    /// How P-frames weight their references, which is `Smart` by default.
+    ///
+    /// The duplicates that `WeightedPrediction::Smart` adds only fit if
+    /// there is more than one reference frame, so with `ref_frames(1)` it
+    /// quietly does no more than `Simple`. Fades gain the most from 2 or
+    /// more reference frames. x264 turns it off for interlaced video, and
+    /// Blu-ray caps it at `Simple`.
+    pub fn weighted_pred(mut self, weightp: WeightedPrediction) -> Self {
+        self.raw.analyse.i_weighted_pred = weightp as i32;
+        self
+    }
+
+    /// Whether B-frames can be kept as references.
+    ///
+    /// This needs at least 2 B-frames, and makes the B-frames that are kept
+    /// report `FrameType::BRef`, which makes the decoded picture buffer
+    /// bigger. Only `FrameType::B` frames can then be dropped.
+    pub fn b_pyramid(mut self, pyramid: BPyramid) -> Self {
+        self.raw.i_bframe_pyramid = pyramid as i32;
+        self
+    }
+
+    /// Lays the frames out in the given number of temporal layers, up to 3,
+    /// so that a relay can drop the top layer to halve the framerate.
+    ///
+    /// One layer has no B-frames, two layers put a B-frame between every pair
+    /// of P-frames, and three layers code 3 B-frames between them, with the
+    /// middle one as a reference. B-frames are always used, so the pattern
+    /// is fixed, and there is a single reference frame, so P-frames never
+    /// depend on a B-frame. `Picture::temporal_id` says which layer each
+    /// frame is in, and the pattern restarts at keyframes.
+    pub fn temporal_layers(mut self, layers: u32) -> Self {
+        let (bframes, pyramid) = match layers {
+            0 | 1 => (0, BPyramid::None),
+            2     => (1, BPyramid::None),
+            _     => (3, BPyramid::Strict),
+        };
+
+        self.raw.i_bframe = bframes;
+        self.raw.i_bframe_pyramid = pyramid as i32;
+        self.raw.i_bframe_adaptive = X264_B_ADAPT_NONE as i32;
+        self.raw.i_frame_reference = 1;
+        self
+    }
+
+    /// Codes the frames with a repeating pattern of types, instead of
+    /// letting x264 pick, such as to match an upstream GOP structure.
+    ///
+    /// This turns off adaptive B-frames and scene cut detection, and allows
+    /// as many consecutive B-frames as the pattern has. A keyframe is still
+    /// forced once `keyint` frames have passed, so the pattern should have
+    /// its keyframes within that. An empty pattern leaves x264 in charge.
+    #[cfg(feature = "alloc")]
+    pub fn frame_type_pattern(mut self, pattern: &[FrameTypeHint]) -> Self {
+        let mut run = 0;
+        for hint in pattern {
+            match *hint {
+                FrameTypeHint::B | FrameTypeHint::BRef => {
+                    run += 1;
+                    self.raw.i_bframe = self.raw.i_bframe.max(run);
+                }
+                _ => run = 0,
+            }
+        }
+
+        self.raw.i_bframe_adaptive = X264_B_ADAPT_NONE as i32;
+        self.raw.i_scenecut_threshold = 0;
+        self.pattern = pattern.to_vec();
+        self
+    }
+
     /// The video's framerate, represented as a rational number.
     ///
     /// The value is in frames per second.
@@ -50,6 +339,16 @@ impl Setup {
         self
     }
 
+    /// The number of frames that will be encoded, if it's known up front.
+    ///
+    /// This is only a hint, which helps rate control converge on content of
+    /// a fixed length. The default of 0 means that it isn't known, such as
+    /// when streaming.
+    pub fn total_frames(mut self, frames: u32) -> Self {
+        self.raw.i_frame_total = frames as i32;
+        self
+    }
+
     /// The encoder's timebase, used in rate control with timestamps.
     ///
     /// The value is in seconds per tick.
@@ -59,17 +358,636 @@ impl Setup {
         self
     }
 
-    /// Please file an issue if you know what this does, because I have no idea.
+    /// Encodes 23.976fps film with a soft pulldown, so that it plays back at
+    /// 29.97fps.
+    ///
+    /// This sets the framerate to `24000 / 1001`, the timebase to `1001 /
+    /// 30000`, and signals the field order of each frame in picture timing
+    /// SEI, which the encoder fills in as the frames come. The timestamps go
+    /// in that timebase, and `Telecine::pts` gives them for each frame. Any
+    /// `fps`, `timebase` or `vfr_input` that is set afterwards breaks it.
+    /// `Telecine::None` turns it back off, but leaves the framerate as is.
+    pub fn telecine(mut self, telecine: Telecine) -> Self {
+        self.telecine = telecine;
+
+        if telecine == Telecine::None {
+            self.raw.b_pulldown = 0;
+            self.raw.b_pic_struct = 0;
+        } else {
+            self.raw.b_pulldown = 1;
+            self.raw.b_pic_struct = 1;
+            self.raw.b_vfr_input = 0;
+            self.raw.i_fps_num = 24000;
+            self.raw.i_fps_den = 1001;
+            self.raw.i_timebase_num = 1001;
+            self.raw.i_timebase_den = 30000;
+        }
+        self
+    }
+
+    /// Whether the timestamps drive rate control, for variable framerate
+    /// input.
+    ///
+    /// When enabled, timestamps must be in `timebase` units, and x264 works
+    /// out how long each frame lasts from the gap to the next timestamp,
+    /// which delays the output by one more frame. When disabled, timestamps
+    /// only order the frames, every frame is assumed to last `1 / fps`, and
+    /// the timebase is replaced with that. This is on by default, except
+    /// with the zero-latency tune.
+    pub fn vfr_input(mut self, vfr: bool) -> Self {
+        self.raw.b_vfr_input = if vfr { 1 } else { 0 };
+        self
+    }
+
+    /// Keeps the headers independent of the content, so that separately
+    /// encoded segments with the same settings can be joined into a single
+    /// stream.
+    ///
+    /// Otherwise, x264 tunes the initial QP in the PPS to the constant QP or
+    /// to the first pass, so the segments' headers could differ. Doing without
+    /// that tuning costs a few bits in every slice header. Each segment still
+    /// has to start with a keyframe, which it does as a fresh encoder.
+    pub fn stitchable(mut self, stitchable: bool) -> Self {
+        self.raw.b_stitchable = if stitchable { 1 } else { 0 };
+        self
+    }
+
+    /// The id of the SPS and the PPS, from 0 to 31, so that streams muxed
+    /// together don't mix their parameter sets up. The default is 0.
+    ///
+    /// x264 only has the one id, which both take, so they can't be set
+    /// apart.
+    pub fn sps_id(mut self, id: u8) -> Self {
+        self.raw.i_sps_id = id as i32;
+        self
+    }
+
+    /// Fully reconstructs every frame, so that `Encoder::reconstructed` can
+    /// give it back, such as to measure the distortion against the source.
+    ///
+    /// x264 keeps the reconstructed frames anyway, so this needs no more
+    /// memory, but it costs the time to deblock frames that nothing refers
+    /// to, which would otherwise be skipped.
+    pub fn full_recon(mut self, full: bool) -> Self {
+        self.raw.b_full_recon = if full { 1 } else { 0 };
+        self
+    }
+
+    /// Works out `Picture::crc32` for every frame that comes out, to check
+    /// that encodes are reproducible.
+    ///
+    /// This turns on `full_recon` too, since the checksum is of the
+    /// reconstructed frame. It is off by default, since it reads every pixel
+    /// of every frame.
+    pub fn recon_crc32(mut self, enabled: bool) -> Self {
+        self.recon_crc32 = enabled;
+        if enabled {
+            self.raw.b_full_recon = 1;
+        }
+        self
+    }
+
+    /// How each unit is framed, which is with a start code by default.
+    ///
+    /// With Annex B framing, as for raw `.h264` files and MPEG-TS, each unit
+    /// starts with `00 00 00 01`, or with `00 00 01` for the slices after
+    /// the first in a frame. Without it, as for MP4 and MKV, each unit
+    /// starts with its length instead, as a 4-byte big-endian number that
+    /// doesn't count itself. This goes for `Encoder::headers` too, so its
+    /// units can go straight into an `avcC` box once the lengths are cut
+    /// off.
     pub fn annexb(mut self, annexb: bool) -> Self {
         self.raw.b_annexb = if annexb { 1 } else { 0 };
         self
     }
 
+    /// The bit depth to encode at, which is 8 by default.
+    ///
+    /// Anything above 8 needs the input to be `Modifier::HighDepth`, and an
+    /// x264 that was built with support for it. `Encoder::encode_8bit` takes
+    /// 8-bit images anyway, by widening them.
+    pub fn bit_depth(mut self, depth: u32) -> Self {
+        self.raw.i_bitdepth = depth as i32;
+        self
+    }
+
+    /// Signals whether displays should overscan the picture.
+    ///
+    /// The default is `Overscan::Unspecified`, which writes nothing.
+    pub fn overscan(mut self, overscan: Overscan) -> Self {
+        self.raw.vui.i_overscan = overscan as i32;
+        self
+    }
+
+    /// Signals whether the samples use their full range, rather than the
+    /// limited range that leaves headroom, such as 16 to 235 for 8-bit luma.
+    ///
+    /// Like the matrix, this has to match the input, since x264 never
+    /// rescales. RGB input is always full range, so that is what gets
+    /// signaled for it unless this says otherwise, and YUV input is taken to
+    /// be limited range.
+    pub fn full_range(mut self, full: bool) -> Self {
+        self.raw.vui.b_fullrange = if full { 1 } else { 0 };
+        self
+    }
+
+    /// Signals the matrix that decoders should use to convert back to RGB.
+    ///
+    /// x264 never converts between RGB and YUV itself, so this has to match
+    /// the input. RGB input is coded as is, which `ColorMatrix::Gbr` signals,
+    /// and is what gets picked by default for it. Building fails if the
+    /// matrix is set to `Gbr` for YUV input, or to anything else for RGB.
+    pub fn color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.raw.vui.i_colmatrix = matrix as i32;
+        self
+    }
+
+    /// Signals that the video is stereoscopic, and how its views are packed.
+    ///
+    /// A frame packing arrangement SEI comes with every keyframe, and with
+    /// every frame for `FramePacking::FrameAlternation`, where it also says
+    /// which view the frame is.
+    pub fn frame_packing(mut self, packing: FramePacking) -> Self {
+        self.raw.i_frame_packing = packing as i32;
+        self
+    }
+
+    /// Repeats the frame packing arrangement SEI every `period_frames`
+    /// frames, counted in input order, so that decoders which join mid-stream
+    /// pick it up sooner.
+    ///
+    /// By default, which is 0, the SEI only comes with keyframes, and a
+    /// repeat that lands on a keyframe means it comes twice. This has no
+    /// effect for `FramePacking::FrameAlternation`, which already has it on
+    /// every frame.
+    #[cfg(feature = "alloc")]
+    pub fn frame_packing_repeat(mut self, period_frames: u32) -> Self {
+        self.packing_repeat = period_frames;
+        self
+    }
+
+    /// Flags the stream as interlaced while still encoding progressive frames.
+    ///
+    /// This is unlike true interlaced encoding, since the fields are never
+    /// coded separately. Some legacy decoders and Blu-ray streams at 25p or
+    /// 30p need it.
+    pub fn fake_interlaced(mut self, fake: bool) -> Self {
+        self.raw.b_fake_interlaced = if fake { 1 } else { 0 };
+        self
+    }
+
+    /// Splits each frame into the given number of slices, which are as even
+    /// as whole rows of macroblocks allow.
+    ///
+    /// Decoders can decode the slices of a frame in parallel, at the cost of
+    /// some compression. Limiting the slice size with `slice_max_size` or
+    /// `slice_max_mbs` overrides this.
+    pub fn slices(mut self, count: u32) -> Self {
+        self.raw.i_slice_count = count as i32;
+        self
+    }
+
+    /// Splits each frame into the given number of slices, which always start
+    /// at the same rows of macroblocks, whatever the content.
+    ///
+    /// This is `slices` with `slice_max_size` and `slice_max_mbs` turned
+    /// off, so the split can't depend on the content. Slices that are
+    /// evenly sized instead of evenly costly leave some decoding threads
+    /// with more work, and each one costs a little compression, since
+    /// prediction stops at its edges. With sliced threads, the count is the
+    /// number of threads instead, which splits frames in the same way.
+    pub fn uniform_slices(mut self, count: u32) -> Self {
+        self.raw.i_slice_count = count as i32;
+        self.raw.i_slice_max_size = 0;
+        self.raw.i_slice_max_mbs = 0;
+        self
+    }
+
+    /// Caps the number of slices in each frame, for when the slices are cut
+    /// by `slice_max_size`.
+    ///
+    /// Once a frame has this many slices, the last of them takes up the rest
+    /// of the frame, however big that makes it. This is useful for decoders
+    /// with a limit on the number of slices.
+    pub fn slice_count_max(mut self, max: u32) -> Self {
+        self.raw.i_slice_count_max = max as i32;
+        self
+    }
+
+    /// Limits the size of each slice, in bytes, including NAL overhead.
+    ///
+    /// This is useful to fit each slice into a single network packet.
+    pub fn slice_max_size(mut self, bytes: u32) -> Self {
+        self.raw.i_slice_max_size = bytes as i32;
+        self
+    }
+
+    /// Limits the number of macroblocks in each slice.
+    ///
+    /// If a byte limit is also set, a slice ends at whichever limit it hits
+    /// first. This takes precedence over a fixed number of slices per frame.
+    pub fn slice_max_mbs(mut self, mbs: u32) -> Self {
+        self.raw.i_slice_max_mbs = mbs as i32;
+        self
+    }
+
+    /// The sizes of the luma quantization deadzones for inter and intra
+    /// blocks, which are clamped to `0..=32`.
+    ///
+    /// Smaller deadzones keep more fine detail, like film grain, at the
+    /// cost of bitrate. Trellis quantization replaces the deadzone wherever
+    /// it is used, so these matter most when trellis is off.
+    pub fn deadzone(mut self, inter: u32, intra: u32) -> Self {
+        self.raw.analyse.i_luma_deadzone = [inter as i32, intra as i32];
+        self
+    }
+
+    /// Whether the in-loop deblocking filter smooths the edges of blocks.
+    ///
+    /// This is on by default, and is applied to every frame type alike. x264
+    /// can't turn it off just for B-frames, since the filter is one setting
+    /// for the whole stream, so the finest control there is comes from the
+    /// strength offsets of `deblock_strength`.
+    pub fn deblock(mut self, enabled: bool) -> Self {
+        self.raw.b_deblocking_filter = if enabled { 1 } else { 0 };
+        self
+    }
+
+    /// Offsets the strength of the deblocking filter, and what counts as an
+    /// edge to smooth, from -6 to 6 each.
+    ///
+    /// Negative values keep more detail, and positive ones smooth more. Both
+    /// default to 0, though some tunes change them.
+    pub fn deblock_strength(mut self, strength: i32, threshold: i32) -> Self {
+        self.raw.i_deblocking_filter_alphac0 = strength;
+        self.raw.i_deblocking_filter_beta = threshold;
+        self
+    }
+
+    /// Whether to entropy code with CABAC, rather than the simpler CAVLC.
+    ///
+    /// This is on by default, and saves around a tenth of the bitrate, but
+    /// it costs speed on both ends. `baseline` turns it off.
+    pub fn cabac(mut self, enabled: bool) -> Self {
+        self.raw.b_cabac = if enabled { 1 } else { 0 };
+        self
+    }
+
+    /// Which of the standard's three tables, from 0 to 2, CABAC starts each
+    /// P- and B-slice from.
+    ///
+    /// This defaults to 0, and x264 never picks another by itself. I-slices
+    /// always use their own table, so this only matters for decoders that
+    /// expect a particular one. Anything above 2 is clamped.
+    pub fn cabac_init_idc(mut self, idc: u8) -> Self {
+        self.raw.i_cabac_init_idc = idc as i32;
+        self
+    }
+
+    /// Whether motion estimation looks at chroma as well as luma.
+    ///
+    /// This is on by default. Turning it off speeds up encoding, at the cost
+    /// of some chroma quality.
+    pub fn chroma_me(mut self, enabled: bool) -> Self {
+        self.raw.analyse.b_chroma_me = if enabled { 1 } else { 0 };
+        self
+    }
+
+    /// Offsets the chroma quantizer from the luma one, from -12 to 12, where
+    /// negative values give chroma more quality. The default is 0.
+    ///
+    /// On top of this, x264 lowers the offset by up to 4 to make up for the
+    /// psychovisual optimizations, which favor luma, and raises it by 6 for
+    /// 4:4:4, as `auto_chroma_qp` explains.
+    pub fn chroma_qp_offset(mut self, offset: i32) -> Self {
+        self.raw.analyse.i_chroma_qp_offset = offset;
+        self
+    }
+
+    /// Keeps chroma as sharp at 4:4:4 as it is at 4:2:0.
+    ///
+    /// With the psychovisual optimizations on, x264 raises the chroma
+    /// quantizer by 6 for 4:4:4 YUV input, which halves the quality of each
+    /// chroma sample, on the grounds that there are four times as many of
+    /// them. That holds up for natural video, but leaves the sharp chroma
+    /// edges of graphics, text and keying soft, which is usually why 4:4:4
+    /// was picked. This takes the 6 back off of `chroma_qp_offset` when
+    /// building for a 4:4:4 colorspace, which costs bitrate.
+    ///
+    /// Nothing changes for other colorspaces, including 4:2:2, which x264
+    /// quantizes the same as 4:2:0, and RGB, which it codes as GBR with no
+    /// chroma to speak of.
+    pub fn auto_chroma_qp(mut self) -> Self {
+        self.auto_chroma_qp = true;
+        self
+    }
+
+    /// The longest that a motion vector can be vertically, in pixels, where
+    /// -1 picks the most that the level allows.
+    ///
+    /// Fast motion at high resolutions can need more than the default, which
+    /// would otherwise clip it. A longer range makes motion estimation
+    /// slower, and anything past what the level allows makes the stream
+    /// break it. x264 clamps the range to `32..=8192`.
+    pub fn mv_range(mut self, pixels: i32) -> Self {
+        self.raw.analyse.i_mv_range = pixels;
+        self
+    }
+
+    /// How far, in rows of pixels, each frame thread stays behind the one
+    /// encoding its reference, where -1 picks automatically.
+    ///
+    /// A frame can only point as far down its reference as that has been
+    /// encoded, so this limits the vertical motion vectors with frame
+    /// threads. Raising it helps with fast vertical motion, but the threads
+    /// wait on each other more. The automatic value splits half of the
+    /// frame's height evenly between the threads.
+    pub fn mv_range_thread(mut self, rows: i32) -> Self {
+        self.raw.analyse.i_mv_range_thread = rows;
+        self
+    }
+
+    /// Turns off the psychovisual optimizations, psy-RD and psy-trellis, to
+    /// benchmark with PSNR or SSIM.
+    ///
+    /// Those optimizations keep detail and grain that the eye expects, even
+    /// where that isn't quite the detail that was there, which looks better
+    /// but measures worse, since the metrics only compare pixels. This keeps
+    /// adaptive quantization, unlike `Tune::Psnr`, which turns it off too,
+    /// and `Tune::Ssim`, which switches it to auto-variance.
+    pub fn disable_psy(self) -> Self {
+        self.psy(false)
+    }
+
+    /// Turns the psychovisual optimizations on or off together.
+    ///
+    /// This is a shorthand for the common case, where psy-RD and
+    /// psy-trellis go together. Off zeroes both, as `disable_psy` does, and
+    /// on puts back the strengths that the preset and tune picked.
+    pub fn psy(mut self, enabled: bool) -> Self {
+        let (rd, trellis) = if enabled { self.psy } else { (0.0, 0.0) };
+        self.raw.analyse.b_psy = if enabled { 1 } else { 0 };
+        self.raw.analyse.f_psy_rd = rd;
+        self.raw.analyse.f_psy_trellis = trellis;
+        self
+    }
+
+    /// Keeps film grain, rather than smoothing it away as noise.
+    ///
+    /// This is `Tune::Grain` in steps, without its changes to rate control,
+    /// and it touches these fields:
+    ///
+    /// - The luma deadzones go down to 14 and 9 for `Light`, 10 and 8 for
+    ///   `Medium`, and 6 and 6 for `Strong`, from 21 and 11, as `deadzone`
+    ///   sets them.
+    /// - DCT decimation is turned off, so that blocks with only a few small
+    ///   coefficients, which is what grain looks like, keep them.
+    /// - Fast P-skip is turned off, so that flat-looking blocks are still
+    ///   analysed rather than skipped.
+    /// - The deblocking strength and threshold go down by 1, 2 and 3, as
+    ///   `deblock_strength` sets them.
+    /// - Psy-trellis goes up to 0.15, 0.25 and 0.35, and the psychovisual
+    ///   optimizations are turned on, so `psy(true)` puts these back.
+    pub fn preserve_grain(mut self, strength: GrainStrength) -> Self {
+        let (deadzone, deblock, trellis) = match strength {
+            GrainStrength::Light  => ([14, 9], -1, 0.15),
+            GrainStrength::Medium => ([10, 8], -2, 0.25),
+            GrainStrength::Strong => ([6, 6], -3, 0.35),
+        };
+
+        self.raw.analyse.i_luma_deadzone = deadzone;
+        self.raw.analyse.b_dct_decimate = 0;
+        self.raw.analyse.b_fast_pskip = 0;
+        self.raw.i_deblocking_filter_alphac0 = deblock;
+        self.raw.i_deblocking_filter_beta = deblock;
+        self.psy.1 = trellis;
+        self.psy(true)
+    }
+
+    /// The subpixel motion estimation and mode decision quality, which x264
+    /// clamps to `0..=11`.
+    ///
+    /// Each level adds to the last. Rate-distortion optimized mode decision
+    /// starts at 6 for I- and P-frames and at 7 for B-frames, RD refinement
+    /// at 8 and 9 in the same way, 10 needs trellis and adaptive
+    /// quantization for QP-RD, and 11 is a full RD.
+    pub fn subme(mut self, level: u32) -> Self {
+        self.raw.analyse.i_subpel_refine = level as i32;
+        self
+    }
+
+    /// Scales the subme that is set, the preset's or `subme`'s, to the size
+    /// that the encoder is built for.
+    ///
+    /// The subme is meant for around 720p to 1080p. Above 1080p, there are
+    /// enough pixels that the finer levels gain little, so it goes down by
+    /// 2, but not below 6, which psy-RD needs. At standard definition and
+    /// below, it is cheap enough to go up by 1, but not above 9, since 10
+    /// and up need trellis and adaptive quantization. It is left alone in
+    /// between, and levels outside of those bounds are never moved further.
+    pub fn auto_subme(mut self) -> Self {
+        self.auto_subme = true;
+        self
+    }
+
+    /// Whether B-frames get rate-distortion optimized mode decision.
+    ///
+    /// x264 has no separate flag for this, so this raises `subme` to 7, or
+    /// lowers it to 6, which also turns off RD refinement.
+    pub fn b_rdo(mut self, enabled: bool) -> Self {
+        let subme = &mut self.raw.analyse.i_subpel_refine;
+        *subme = if enabled { (*subme).max(7) } else { (*subme).min(6) };
+        self
+    }
+
+    /// Whether the modes picked by RDO get refined, in every frame type.
+    ///
+    /// x264 has no separate flag for this, so this raises `subme` to 9, or
+    /// lowers it to 7, which keeps RDO for B-frames.
+    pub fn rd_refinement(mut self, enabled: bool) -> Self {
+        let subme = &mut self.raw.analyse.i_subpel_refine;
+        *subme = if enabled { (*subme).max(9) } else { (*subme).min(7) };
+        self
+    }
+
     /// Approximately restricts the bitrate.
     ///
-    /// The value is in metric kilobits per second.
-    pub fn bitrate(mut self, bitrate: i32) -> Self {
-        self.raw.rc.i_bitrate = bitrate;
+    /// This is the target of `RateControl::AverageBitrate`, as set up by
+    /// `live_cbr`, and is ignored at a constant rate factor or QP.
+    pub fn bitrate(mut self, bitrate: BitRate) -> Self {
+        self.raw.rc.i_bitrate = bitrate.as_kbps() as i32;
+        self
+    }
+
+    /// How much finer the quantizer is for I-frames than for P-frames, as a
+    /// ratio of their quantizer scales. The default is 1.4.
+    ///
+    /// x264 treats an I-frame at a scene cut the same as any other, so this
+    /// is also the boost that scene cuts get. Raising it avoids the quality
+    /// dip after a cut in ABR mode, at the cost of bits for the frames that
+    /// follow.
+    pub fn ip_ratio(mut self, ratio: f32) -> Self {
+        self.raw.rc.f_ip_factor = ratio;
+        self
+    }
+
+    /// Restricts the bitrate with a video buffering verifier.
+    ///
+    /// The size of the buffer is in metric kilobits, so a buffer as big as
+    /// the maximum bitrate in kilobits per second holds one second.
+    pub fn vbv(mut self, max_bitrate: BitRate, buffer_size: i32) -> Self {
+        self.raw.rc.i_vbv_max_bitrate = max_bitrate.as_kbps() as i32;
+        self.raw.rc.i_vbv_buffer_size = buffer_size;
+        self
+    }
+
+    /// Signals HRD parameters, for strict decoders and broadcast.
+    ///
+    /// This needs `vbv` to be set, since the parameters are derived from it.
+    /// Each keyframe then comes with a buffering period SEI and every frame
+    /// with a picture timing SEI, as `NalUnitType::Sei` units before its
+    /// slices. The default is `NalHrd::None`.
+    pub fn nal_hrd(mut self, hrd: NalHrd) -> Self {
+        self.raw.i_nal_hrd = hrd as i32;
+        self
+    }
+
+    /// Sets everything up for strict constant bitrate live streaming, in a
+    /// single pass and without a stats file.
+    ///
+    /// This sets:
+    ///
+    /// - the rate control to `RateControl::AverageBitrate`, at `bitrate`;
+    /// - the VBV to the same maximum bitrate, with a buffer of one second;
+    /// - `NalHrd::Cbr`, which also turns on filler data to pad the bitrate;
+    /// - the framerate, which the VBV needs to track the buffer;
+    /// - what `ultra_low_latency` does;
+    /// - and a closed GOP.
+    ///
+    /// x264 quietly falls back to VBR HRD unless the bitrate and the maximum
+    /// bitrate are equal, so changing `bitrate` or `vbv` afterwards should
+    /// keep them that way.
+    pub fn live_cbr(
+        mut self,
+        bitrate: BitRate,
+        fps_num: u32,
+        fps_den: u32,
+    ) -> Self {
+        let kbps = bitrate.as_kbps() as i32;
+        self.raw.rc.i_rc_method = X264_RC_ABR as i32;
+        self.raw.rc.i_bitrate = kbps;
+        self.raw.rc.i_vbv_max_bitrate = kbps;
+        self.raw.rc.i_vbv_buffer_size = kbps;
+        self.raw.i_nal_hrd = X264_NAL_HRD_CBR as i32;
+        self.raw.rc.b_filler = 1;
+        self.raw.i_fps_num = fps_num;
+        self.raw.i_fps_den = fps_den;
+        self.raw.rc.i_lookahead = 0;
+        self.raw.i_sync_lookahead = 0;
+        self.raw.i_bframe = 0;
+        self.raw.rc.b_mb_tree = 0;
+        self.raw.b_sliced_threads = 1;
+        self.raw.b_vfr_input = 0;
+        self.raw.b_open_gop = 0;
+        self
+    }
+
+    /// Sets everything up for the lowest latency that x264 can manage, as
+    /// for screen sharing, so that each frame comes out of the same call
+    /// that fed it in.
+    ///
+    /// This sets what `zero_latency` does, which is no lookahead, no
+    /// B-frames, no macroblock tree, sliced threads, and no variable
+    /// framerate input, and makes sure that there is no sync lookahead
+    /// either. x264 only outputs whole frames, so the slices can't be sent
+    /// any sooner, but the threads encode one slice each of the same frame,
+    /// which cuts the time each frame takes. x264 makes as many slices as
+    /// there are threads, so `threads` picks the slice count too, though
+    /// x264 caps it at one slice for every 4 rows of macroblocks.
+    ///
+    /// Without this, x264 holds back a frame for each extra frame thread,
+    /// plus the lookahead, which is 40 frames for `Preset::Medium`, plus
+    /// the B-frames. With it, `Flush` never has any frames to drain.
+    pub fn ultra_low_latency(mut self) -> Self {
+        self.raw.b_sliced_threads = 1;
+        self.raw.rc.i_lookahead = 0;
+        self.raw.i_sync_lookahead = 0;
+        self.raw.i_bframe = 0;
+        self.raw.rc.b_mb_tree = 0;
+        self.raw.b_vfr_input = 0;
+        self
+    }
+
+    /// Sets the lookahead, the B-frames and the threading together, for a
+    /// target latency.
+    ///
+    /// x264 has two lookaheads. The rate control lookahead, `rc.i_lookahead`,
+    /// is how many frames the macroblock tree and the VBV plan over, and the
+    /// frame type decision looks as far ahead as the B-frames, or four times
+    /// that with the trellis B-frame decision. The sync lookahead,
+    /// `i_sync_lookahead`, is a buffer of frames that lets the lookahead
+    /// thread run ahead of the frame threads. The frames held back add up
+    /// from both lookaheads, one for each frame thread after the first, and
+    /// one for variable framerate input.
+    ///
+    /// - `UltraLow` does what `ultra_low_latency` does, so nothing is held
+    ///   back.
+    /// - `Low` looks 10 frames ahead, with the macroblock tree, but no
+    ///   B-frames or sync lookahead, sliced threads and no variable
+    ///   framerate input.
+    /// - `Balanced` looks 30 frames ahead, with 3 B-frames, frame threads,
+    ///   and a sync lookahead that x264 picks.
+    /// - `Quality` looks 60 frames ahead, with 5 B-frames, frame threads,
+    ///   and a sync lookahead that x264 picks.
+    ///
+    /// `Encoder::estimated_latency_frames` tells how many frames this comes
+    /// to, once x264 has picked the thread count.
+    pub fn latency_profile(mut self, profile: LatencyProfile) -> Self {
+        let (lookahead, bframes, sliced) = match profile {
+            LatencyProfile::UltraLow => return self.ultra_low_latency(),
+            LatencyProfile::Low      => (10, 0, true),
+            LatencyProfile::Balanced => (30, 3, false),
+            LatencyProfile::Quality  => (60, 5, false),
+        };
+
+        self.raw.rc.i_lookahead = lookahead;
+        self.raw.rc.b_mb_tree = 1;
+        self.raw.i_bframe = bframes;
+        self.raw.b_sliced_threads = if sliced { 1 } else { 0 };
+
+        if sliced {
+            self.raw.i_sync_lookahead = 0;
+            self.raw.b_vfr_input = 0;
+        } else {
+            self.raw.i_sync_lookahead = X264_SYNC_LOOKAHEAD_AUTO;
+        }
+        self
+    }
+
+    /// Restricts the stream to what Blu-ray players accept.
+    ///
+    /// x264 quietly overrides some settings to fit: there are at most 3
+    /// B-frames and 6 reference frames, a strict B-pyramid at most, and scene
+    /// cuts always become IDR frames. Building fails for the settings that
+    /// it can't keep at all, which are intra refresh and slice limits, and
+    /// if `vbv` isn't set, since Blu-ray needs the HRD parameters that are
+    /// derived from it.
+    pub fn bluray_compat(mut self, compat: bool) -> Self {
+        self.raw.b_bluray_compat = if compat { 1 } else { 0 };
+        self
+    }
+
+    /// Encodes to the AVC-Intra format, for professional intra-only workflows.
+    ///
+    /// This overrides most other settings: every frame is an IDR frame,
+    /// coded at 10 bits with fixed quantization matrices and a constant
+    /// bitrate, and the profile follows from the class. The input must be
+    /// `Modifier::HighDepth`, and the colorspace, resolution and framerate
+    /// must be ones the class defines, or building fails. x264 has no 480
+    /// class in this version.
+    pub fn avc_intra(mut self, class: AvcIntraClass) -> Self {
+        self.raw.i_avcintra_class = class as i32;
+        self.raw.i_bitdepth = 10;
         self
     }
 
@@ -106,7 +1024,102 @@ impl Setup {
         self
     }
 
+    /// Pins the H.264 level, instead of letting x264 pick the lowest that fits.
+    ///
+    /// x264 clamps some settings to fit the level, so a low level may
+    /// silently reduce the number of reference frames. Building fails if the
+    /// resolution exceeds the level's maximum frame size.
+    pub fn level(mut self, level: Level) -> Self {
+        self.raw.i_level_idc = level.idc();
+        self
+    }
+
+    /// Gives direct access to the underlying parameters, for any options that
+    /// don't have a setter.
+    ///
+    /// The closure runs right away, so setters called afterwards still win.
+    /// The colorspace, width and height are always overwritten by `build`.
+    ///
+    /// # Unsafety
+    ///
+    /// x264 trusts these parameters, so the caller must ensure that they are
+    /// valid. In particular, any pointers, such as the log callback or file
+    /// names, must stay valid for as long as the encoder.
+    pub unsafe fn with_raw_params<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut x264_param_t),
+    {
+        f(&mut self.raw);
+        self
+    }
+
+    /// Checks for settings that are legal but probably not what was meant.
+    ///
+    /// This doesn't stop `build` from succeeding, so the warnings are only
+    /// advice, meant to be shown to the user.
+    #[cfg(feature = "alloc")]
+    pub fn validate(&self) -> Vec<Warning> {
+        let raw = &self.raw;
+        let rc = &raw.rc;
+        let mut warnings = Vec::new();
+
+        let vbv = rc.i_vbv_max_bitrate > 0 && rc.i_vbv_buffer_size > 0;
+        let cqp = rc.i_rc_method == X264_RC_CQP as i32;
+        let hrd = raw.i_nal_hrd != X264_NAL_HRD_NONE as i32;
+
+        if raw.b_sliced_threads != 0 && (
+            rc.i_lookahead > 0 ||
+            raw.i_sync_lookahead > 0 ||
+            raw.i_bframe > 0
+        ) {
+            warnings.push(Warning::LatencyFromLookahead);
+        }
+
+        if (rc.i_vbv_max_bitrate > 0) != (rc.i_vbv_buffer_size > 0) {
+            warnings.push(Warning::IncompleteVbv);
+        }
+
+        if cqp && (rc.i_vbv_max_bitrate > 0 || rc.i_vbv_buffer_size > 0) {
+            warnings.push(Warning::VbvWithConstantQp);
+        } else if vbv && !hrd {
+            warnings.push(Warning::VbvWithoutHrd);
+        }
+
+        if hrd && (!vbv || cqp) {
+            warnings.push(Warning::HrdWithoutVbv);
+        } else if raw.i_nal_hrd == X264_NAL_HRD_CBR as i32 &&
+            rc.i_bitrate != rc.i_vbv_max_bitrate
+        {
+            warnings.push(Warning::CbrWithoutMatchingBitrate);
+        }
+
+        if raw.i_bframe_pyramid != X264_B_PYRAMID_NONE as i32 &&
+            raw.i_bframe == 1
+        {
+            warnings.push(Warning::PyramidWithoutBframes);
+        }
+
+        if raw.analyse.i_weighted_pred == X264_WEIGHTP_SMART as i32 &&
+            raw.i_frame_reference < 2
+        {
+            warnings.push(Warning::SmartWeightpWithOneRef);
+        }
+
+        if raw.b_intra_refresh != 0 && raw.i_frame_reference > 1 {
+            warnings.push(Warning::IntraRefreshWithRefs);
+        }
+
+        warnings
+    }
+
     /// Build the encoder.
+    ///
+    /// This fails with `Error::Failed` if x264 rejects the settings, such as
+    /// a width or height of 0. x264 logs why to stderr, but the error has no
+    /// more detail. Catching the message would take a log callback, which
+    /// x264 passes a C `va_list`, whose Rust type differs from one target to
+    /// the next, and `Error` is `Copy` and has to work without `alloc`, so
+    /// it couldn't hold the text anyway.
     pub fn build<C>(
         mut self,
         csp: C,
@@ -116,20 +1129,100 @@ impl Setup {
     where
         C: Into<Encoding>,
     {
-        self.raw.i_csp = csp.into().into_raw();
+        if let Some(level) = Level::from_idc(self.raw.i_level_idc) {
+            if !level.fits(width, height) {
+                return Err(Error::Failed);
+            }
+
+            if self.refs_for_level {
+                // Interlaced frames are a whole number of field pairs.
+                let interlaced = self.raw.b_interlaced != 0
+                    || self.raw.b_fake_interlaced != 0;
+                let rows = if interlaced {
+                    (height + 31) & !31
+                } else {
+                    height
+                };
+                let refs = level.max_refs(width, rows);
+                self.raw.i_frame_reference = refs as i32;
+            }
+        }
+
+        if self.raw.b_bluray_compat != 0 && (
+            self.raw.b_intra_refresh != 0 ||
+            self.raw.i_slice_max_size != 0 ||
+            self.raw.i_slice_max_mbs != 0 ||
+            self.raw.rc.i_vbv_max_bitrate <= 0 ||
+            self.raw.rc.i_vbv_buffer_size <= 0
+        ) {
+            return Err(Error::Failed);
+        }
+
+        if self.auto_subme {
+            let mbs = ((width + 15) / 16) * ((height + 15) / 16);
+            let subme = self.raw.analyse.i_subpel_refine;
+            self.raw.analyse.i_subpel_refine = if mbs > 8160 {
+                (subme - 2).max(subme.min(6))
+            } else if mbs <= 1620 {
+                (subme + 1).min(subme.max(9))
+            } else {
+                subme
+            };
+        }
+
+        let csp = csp.into();
+        if self.auto_chroma_qp
+            && self.raw.analyse.b_psy != 0
+            && csp.subsampling() == Subsampling::Yuv444
+        {
+            self.raw.analyse.i_chroma_qp_offset -= 6;
+        }
+
+        let gbr = self.raw.vui.i_colmatrix == ColorMatrix::Gbr as i32;
+        let rgb = csp.subsampling() == Subsampling::Rgb;
+        if self.raw.vui.i_colmatrix >= 0 && gbr != rgb {
+            return Err(Error::Failed);
+        }
+
+        self.raw.i_csp = csp.into_raw();
         self.raw.i_width = width;
         self.raw.i_height = height;
 
         let raw = unsafe { x264_encoder_open(&mut self.raw) };
 
         if raw.is_null() {
-            Err(Error)
+            Err(Error::Failed)
         } else {
-            Ok(unsafe { Encoder::from_raw(raw) })
+            let mut encoder = unsafe { Encoder::from_raw_parts(raw, self.raw) };
+            encoder.set_pulldown(self.telecine.pattern());
+            encoder.set_recon_crc32(self.recon_crc32);
+            #[cfg(feature = "alloc")]
+            encoder.set_frame_type_pattern(self.pattern);
+            #[cfg(feature = "alloc")]
+            encoder.set_frame_packing_repeat(self.packing_repeat);
+            Ok(encoder)
         }
     }
 }
 
+impl fmt::Debug for Setup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let raw = &self.raw;
+
+        f.debug_struct("Setup")
+            .field("rate_control", &RateControl::from_raw(raw.rc.i_rc_method))
+            .field("bitrate", &raw.rc.i_bitrate)
+            .field("fps", &(raw.i_fps_num, raw.i_fps_den))
+            .field("threads", &raw.i_threads)
+            .field("keyint", &raw.i_keyint_max)
+            .field("bframes", &raw.i_bframe)
+            .field("ref_frames", &raw.i_frame_reference)
+            .field("subme", &raw.analyse.i_subpel_refine)
+            .field("level", &Level::from_idc(raw.i_level_idc))
+            .finish()
+    }
+}
+
 impl Default for Setup {
     fn default() -> Self {
         let raw = unsafe {
@@ -138,6 +1231,18 @@ impl Default for Setup {
             raw.assume_init()
         };
 
-        Self { raw }
+        Self {
+            raw,
+            psy: (raw.analyse.f_psy_rd, raw.analyse.f_psy_trellis),
+            telecine: Telecine::None,
+            refs_for_level: false,
+            auto_subme: false,
+            auto_chroma_qp: false,
+            recon_crc32: false,
+            #[cfg(feature = "alloc")]
+            pattern: Vec::new(),
+            #[cfg(feature = "alloc")]
+            packing_repeat: 0,
+        }
     }
 }
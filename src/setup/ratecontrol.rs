@@ -0,0 +1,108 @@
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// How the encoder decides how many bits to spend on each frame.
+pub enum RateControl {
+    /// Every frame is coded at a constant QP.
+    ConstantQp = X264_RC_CQP as i32,
+    /// The quality is kept constant, letting the bitrate vary.
+    ConstantRateFactor = X264_RC_CRF as i32,
+    /// The bitrate is kept close to a target on average.
+    AverageBitrate = X264_RC_ABR as i32,
+}
+
+impl RateControl {
+    #[doc(hidden)]
+    pub fn from_raw(raw: i32) -> Self {
+        use self::RateControl::*;
+
+        match raw as u32 {
+            X264_RC_CQP => ConstantQp,
+            X264_RC_CRF => ConstantRateFactor,
+            _           => AverageBitrate,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// Which hypothetical reference decoder parameters get signaled.
+pub enum NalHrd {
+    /// Don't signal any.
+    None = X264_NAL_HRD_NONE as i32,
+    /// Signal a variable bitrate.
+    Vbr = X264_NAL_HRD_VBR as i32,
+    /// Signal a constant bitrate, padding the stream with filler data.
+    Cbr = X264_NAL_HRD_CBR as i32,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// A bitrate, which x264 takes in whole metric kilobits per second.
+///
+/// The constructors round to the nearest kilobit, and cap the rate at what
+/// x264 can store, which is over two terabits per second.
+pub struct BitRate {
+    kbps: u32,
+}
+
+impl BitRate {
+    /// A bitrate in metric kilobits per second, as x264 takes it.
+    pub fn kbps(kbps: u32) -> Self {
+        Self::new(kbps as u64)
+    }
+
+    /// A bitrate in metric megabits per second, so `mbps(2.5)` is 2500
+    /// kilobits per second. Negative rates and NaN become 0.
+    pub fn mbps(mbps: f32) -> Self {
+        Self::new((mbps as f64 * 1000.0 + 0.5) as u64)
+    }
+
+    /// A bitrate in bits per second, so `bps(2_500_000)` is 2500 kilobits
+    /// per second.
+    pub fn bps(bps: u64) -> Self {
+        Self::new(bps.saturating_add(500) / 1000)
+    }
+
+    /// The bitrate in metric kilobits per second.
+    pub fn as_kbps(self) -> u32 {
+        self.kbps
+    }
+
+    fn new(kbps: u64) -> Self {
+        BitRate { kbps: kbps.min(i32::MAX as u64) as u32 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(BitRate::kbps(2500).as_kbps(), 2500);
+        assert_eq!(BitRate::mbps(2.0).as_kbps(), 2000);
+        assert_eq!(BitRate::mbps(2.5).as_kbps(), 2500);
+        assert_eq!(BitRate::bps(2_500_000).as_kbps(), 2500);
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(BitRate::mbps(0.0014).as_kbps(), 1);
+        assert_eq!(BitRate::mbps(0.0016).as_kbps(), 2);
+        assert_eq!(BitRate::bps(1499).as_kbps(), 1);
+        assert_eq!(BitRate::bps(1500).as_kbps(), 2);
+        assert_eq!(BitRate::bps(499).as_kbps(), 0);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let max = i32::MAX as u32;
+        assert_eq!(BitRate::mbps(-1.0).as_kbps(), 0);
+        assert_eq!(BitRate::mbps(f32::NAN).as_kbps(), 0);
+        assert_eq!(BitRate::mbps(f32::INFINITY).as_kbps(), max);
+        assert_eq!(BitRate::mbps(1e12).as_kbps(), max);
+        assert_eq!(BitRate::kbps(u32::MAX).as_kbps(), max);
+        assert_eq!(BitRate::bps(u64::MAX).as_kbps(), max);
+    }
+}
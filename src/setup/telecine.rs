@@ -0,0 +1,87 @@
+use x264::*;
+
+const TB: i32 = pic_struct_e_PIC_STRUCT_TOP_BOTTOM as i32;
+const BT: i32 = pic_struct_e_PIC_STRUCT_BOTTOM_TOP as i32;
+const TBT: i32 = pic_struct_e_PIC_STRUCT_TOP_BOTTOM_TOP as i32;
+const BTB: i32 = pic_struct_e_PIC_STRUCT_BOTTOM_TOP_BOTTOM as i32;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+/// A soft pulldown, which tells decoders to repeat fields so that 23.976fps
+/// film plays back at 29.97fps.
+///
+/// Only the field repetition is signalled, so the frames are coded just
+/// once, at the film rate.
+pub enum Telecine {
+    /// No pulldown, so every frame is shown once.
+    None,
+    /// The classic 3:2 pulldown, which repeats a field on every other frame.
+    Pulldown32,
+    /// The 2:3:3:2 pulldown, which keeps every fifth displayed frame whole,
+    /// so it's easier to undo.
+    Pulldown2332,
+}
+
+impl Telecine {
+    /// The timestamp of the given frame, in the `1001 / 30000` second ticks
+    /// that the pulldown uses as its timebase.
+    ///
+    /// Each field lasts half a tick, and is rounded the same way as the x264
+    /// command line does. Without a pulldown, this is just the frame number.
+    pub fn pts(self, frame: u64) -> i64 {
+        let pattern = self.pattern();
+
+        if pattern.is_empty() {
+            return frame as i64;
+        }
+
+        // Every pattern is 4 frames over 10 fields.
+        let fields: u64 = frame / 4 * 10 + pattern[..(frame % 4) as usize]
+            .iter()
+            .map(|&s| if s == TBT || s == BTB { 3 } else { 2 })
+            .sum::<u64>();
+
+        (fields / 2 + fields % 2) as i64
+    }
+
+    #[doc(hidden)]
+    pub fn pattern(self) -> &'static [i32] {
+        match self {
+            Telecine::None         => &[],
+            Telecine::Pulldown32   => &[TBT, BT, BTB, TB],
+            Telecine::Pulldown2332 => &[TB, TBT, BTB, TB],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pattern: &[i32]) -> u32 {
+        pattern.iter()
+            .map(|&s| if s == TBT || s == BTB { 3 } else { 2 })
+            .sum()
+    }
+
+    #[test]
+    fn four_frames_are_ten_fields() {
+        assert_eq!(fields(Telecine::Pulldown32.pattern()), 10);
+        assert_eq!(fields(Telecine::Pulldown2332.pattern()), 10);
+        assert!(Telecine::None.pattern().is_empty());
+    }
+
+    #[test]
+    fn pts() {
+        let pts = |telecine: Telecine| {
+            let mut pts = [0; 9];
+            for (frame, pts) in pts.iter_mut().enumerate() {
+                *pts = telecine.pts(frame as u64);
+            }
+            pts
+        };
+
+        assert_eq!(pts(Telecine::None), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(pts(Telecine::Pulldown32), [0, 2, 3, 4, 5, 7, 8, 9, 10]);
+        assert_eq!(pts(Telecine::Pulldown2332), [0, 1, 3, 4, 5, 6, 8, 9, 10]);
+    }
+}
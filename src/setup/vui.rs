@@ -0,0 +1,45 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// How a display should treat the edges of the picture.
+pub enum Overscan {
+    /// Leave it up to the display.
+    Unspecified = 0,
+    /// Show the whole picture, since the edges contain useful content.
+    Show = 1,
+    /// Crop the edges, since they are safe to lose.
+    Crop = 2,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// The matrix a decoder should use to turn the coded planes back into RGB.
+pub enum ColorMatrix {
+    /// The planes are already G, B and R.
+    Gbr = 0,
+    /// ITU-R BT.709, for HD.
+    Bt709 = 1,
+    /// Leave it up to the decoder.
+    Undefined = 2,
+    /// FCC Title 47.
+    Fcc = 4,
+    /// ITU-R BT.470 System B/G, for PAL.
+    Bt470bg = 5,
+    /// SMPTE 170M, for NTSC.
+    Smpte170m = 6,
+    /// SMPTE 240M.
+    Smpte240m = 7,
+    /// YCgCo.
+    YCgCo = 8,
+    /// ITU-R BT.2020, non-constant luminance.
+    Bt2020Nc = 9,
+    /// ITU-R BT.2020, constant luminance.
+    Bt2020C = 10,
+    /// SMPTE ST 2085.
+    Smpte2085 = 11,
+    /// Derived from the chromaticity, non-constant luminance.
+    ChromaDerivedNc = 12,
+    /// Derived from the chromaticity, constant luminance.
+    ChromaDerivedC = 13,
+    /// ITU-R BT.2100 ICtCp.
+    ICtCp = 14,
+}
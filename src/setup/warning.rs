@@ -0,0 +1,154 @@
+use core::fmt;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+/// A setting that works, but probably not the way it was meant to.
+///
+/// These come from `Setup::validate`, and none of them stop `build` from
+/// succeeding.
+pub enum Warning {
+    /// Sliced threads are on, as for low latency, but the lookahead or the
+    /// B-frames still hold frames back.
+    LatencyFromLookahead,
+    /// The VBV is set, but no HRD parameters are signaled, so decoders can't
+    /// rely on it.
+    VbvWithoutHrd,
+    /// HRD parameters are asked for, but the VBV that they are derived from
+    /// isn't set, so x264 leaves them out.
+    HrdWithoutVbv,
+    /// CBR HRD is asked for, but the bitrate and the maximum bitrate differ,
+    /// so x264 signals VBR instead.
+    CbrWithoutMatchingBitrate,
+    /// Only one of the VBV's maximum bitrate and buffer size is set, so x264
+    /// ignores it.
+    IncompleteVbv,
+    /// The VBV is set, but it is ignored at a constant QP.
+    VbvWithConstantQp,
+    /// A B-pyramid is asked for, but there is only 1 B-frame, so it is
+    /// turned off.
+    PyramidWithoutBframes,
+    /// Smart weighted prediction is on, but with a single reference frame,
+    /// there is no room for its duplicates, so it does no more than simple.
+    SmartWeightpWithOneRef,
+    /// Intra refresh is on, but it only supports a single reference frame,
+    /// so x264 drops the rest.
+    IntraRefreshWithRefs,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Warning::*;
+
+        f.write_str(match *self {
+            LatencyFromLookahead =>
+                "sliced threads are on, but the lookahead or B-frames add \
+                 latency",
+            VbvWithoutHrd =>
+                "VBV is set without HRD parameters",
+            HrdWithoutVbv =>
+                "HRD parameters need VBV, so they are left out",
+            CbrWithoutMatchingBitrate =>
+                "CBR HRD needs the bitrate to equal the VBV maximum, so VBR \
+                 is signaled",
+            IncompleteVbv =>
+                "VBV needs both a maximum bitrate and a buffer size, so it is \
+                 ignored",
+            VbvWithConstantQp =>
+                "VBV is ignored at a constant QP",
+            PyramidWithoutBframes =>
+                "B-pyramid needs at least 2 B-frames, so it is turned off",
+            SmartWeightpWithOneRef =>
+                "smart weighted prediction needs 2 reference frames to \
+                 duplicate them",
+            IntraRefreshWithRefs =>
+                "intra refresh only supports 1 reference frame",
+        })
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use x264::*;
+    use {BitRate, NalHrd, Preset, Setup, Tune};
+
+    fn medium() -> Setup {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+    }
+
+    fn warns(setup: Setup, warning: Warning) {
+        assert_eq!(setup.validate(), [warning]);
+    }
+
+    #[test]
+    fn presets_are_clean() {
+        use self::Preset::*;
+
+        for &preset in &[
+            Ultrafast, Superfast, Veryfast, Faster, Fast,
+            Medium, Slow, Slower, Veryslow, Placebo,
+        ] {
+            for &zero_latency in &[false, true] {
+                let setup =
+                    Setup::preset(preset, Tune::None, false, zero_latency);
+                assert!(setup.validate().is_empty(), "{:?}", preset);
+            }
+        }
+    }
+
+    #[test]
+    fn latency_from_lookahead() {
+        warns(medium().sliced_threads(true), Warning::LatencyFromLookahead);
+    }
+
+    #[test]
+    fn vbv_without_hrd() {
+        let rate = BitRate::kbps(1000);
+        warns(medium().bitrate(rate).vbv(rate, 1000), Warning::VbvWithoutHrd);
+    }
+
+    #[test]
+    fn hrd_without_vbv() {
+        warns(medium().nal_hrd(NalHrd::Vbr), Warning::HrdWithoutVbv);
+    }
+
+    #[test]
+    fn cbr_without_matching_bitrate() {
+        let setup = medium()
+            .bitrate(BitRate::kbps(1000))
+            .vbv(BitRate::kbps(2000), 2000)
+            .nal_hrd(NalHrd::Cbr);
+        warns(setup, Warning::CbrWithoutMatchingBitrate);
+    }
+
+    #[test]
+    fn incomplete_vbv() {
+        warns(medium().vbv(BitRate::kbps(1000), 0), Warning::IncompleteVbv);
+    }
+
+    #[test]
+    fn vbv_with_constant_qp() {
+        let setup = unsafe {
+            medium().with_raw_params(|raw| {
+                raw.rc.i_rc_method = X264_RC_CQP as i32;
+            })
+        };
+        let setup = setup.vbv(BitRate::kbps(1000), 1000);
+        warns(setup, Warning::VbvWithConstantQp);
+    }
+
+    #[test]
+    fn pyramid_without_bframes() {
+        warns(medium().bframes(1), Warning::PyramidWithoutBframes);
+    }
+
+    #[test]
+    fn smart_weightp_with_one_ref() {
+        warns(medium().ref_frames(1), Warning::SmartWeightpWithOneRef);
+    }
+
+    #[test]
+    fn intra_refresh_with_refs() {
+        let setup = medium().intra_refresh_period(60);
+        warns(setup, Warning::IntraRefreshWithRefs);
+    }
+}
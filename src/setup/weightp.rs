@@ -0,0 +1,14 @@
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// How P-frames weight their references, which helps with fades.
+pub enum WeightedPrediction {
+    /// References are never weighted.
+    None = X264_WEIGHTP_NONE as i32,
+    /// Each reference can be weighted, as Blu-ray allows.
+    Simple = X264_WEIGHTP_SIMPLE as i32,
+    /// Like `Simple`, and the nearest reference can also be duplicated with
+    /// other weights, taking the place of an older reference.
+    Smart = X264_WEIGHTP_SMART as i32,
+}
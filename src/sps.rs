@@ -0,0 +1,346 @@
+/// The fields of a sequence parameter set that muxers care about.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct SpsInfo {
+    /// The profile, such as 100 for High.
+    pub profile_idc: u8,
+    /// The constraint flags, as the byte that follows the profile.
+    pub constraint_flags: u8,
+    /// The level, times ten, such as 31 for level 3.1.
+    pub level_idc: u8,
+    /// The id that PPSs refer to this SPS by.
+    pub sps_id: u32,
+    /// The chroma format, where 0 is monochrome, 1 is 4:2:0, 2 is 4:2:2 and
+    /// 3 is 4:4:4.
+    pub chroma_format_idc: u32,
+    /// The bit depth of luma samples.
+    pub bit_depth_luma: u32,
+    /// The bit depth of chroma samples.
+    pub bit_depth_chroma: u32,
+    /// The maximum number of reference frames.
+    pub max_num_ref_frames: u32,
+    /// Whether every picture is a frame, rather than possibly a field.
+    pub frame_mbs_only: bool,
+    /// The width, in pixels, after cropping.
+    pub width: u32,
+    /// The height, in pixels, after cropping.
+    pub height: u32,
+    /// The sample aspect ratio from the VUI, if there is one.
+    pub sar: Option<(u16, u16)>,
+    /// Whether the VUI says that the edges can be cropped by overscanning,
+    /// if it says either way.
+    pub overscan: Option<bool>,
+    /// Whether the VUI signals that the samples use their full range.
+    pub full_range: bool,
+    /// The `matrix_coefficients` from the VUI, if there are any, which
+    /// match `ColorMatrix`.
+    pub matrix: Option<u8>,
+    /// The `num_units_in_tick` and `time_scale` from the VUI, if there are
+    /// any. A frame lasts two ticks.
+    pub timing: Option<(u32, u32)>,
+}
+
+impl SpsInfo {
+    /// Parses an SPS unit, with or without its start code.
+    ///
+    /// This gives `None` if the unit isn't an SPS, or if it's truncated or
+    /// otherwise malformed.
+    pub fn parse(unit: &[u8]) -> Option<Self> {
+        let unit = strip_start_code(unit);
+
+        if unit.first()? & 0x1F != 7 {
+            return None;
+        }
+
+        let mut r = BitReader::new(&unit[1..]);
+
+        let profile_idc = r.bits(8)? as u8;
+        let constraint_flags = r.bits(8)? as u8;
+        let level_idc = r.bits(8)? as u8;
+        let sps_id = r.ue()?;
+
+        let mut chroma_format_idc = 1;
+        let mut separate_colour_plane = false;
+        let mut bit_depth_luma = 8;
+        let mut bit_depth_chroma = 8;
+
+        match profile_idc {
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 |
+            134 | 135 => {
+                chroma_format_idc = r.ue()?;
+                if chroma_format_idc == 3 {
+                    separate_colour_plane = r.flag()?;
+                }
+                bit_depth_luma = r.ue()?.checked_add(8)?;
+                bit_depth_chroma = r.ue()?.checked_add(8)?;
+                r.flag()?; // qpprime_y_zero_transform_bypass_flag
+
+                if r.flag()? {
+                    let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+                    for i in 0..lists {
+                        if r.flag()? {
+                            r.scaling_list(if i < 6 { 16 } else { 64 })?;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        r.ue()?; // log2_max_frame_num_minus4
+        match r.ue()? {
+            0 => { r.ue()?; }
+            1 => {
+                r.flag()?;
+                r.se()?;
+                r.se()?;
+                for _ in 0..r.ue()? {
+                    r.se()?;
+                }
+            }
+            _ => {}
+        }
+
+        let max_num_ref_frames = r.ue()?;
+        r.flag()?; // gaps_in_frame_num_value_allowed_flag
+        let width_mbs = r.ue()? + 1;
+        let height_map_units = r.ue()? + 1;
+        let frame_mbs_only = r.flag()?;
+        if !frame_mbs_only {
+            r.flag()?; // mb_adaptive_frame_field_flag
+        }
+        r.flag()?; // direct_8x8_inference_flag
+
+        let fields = if frame_mbs_only { 1 } else { 2 };
+        let mut width = width_mbs.checked_mul(16)?;
+        let mut height = height_map_units.checked_mul(16 * fields)?;
+
+        if r.flag()? {
+            let (sub_width, sub_height) =
+                match (separate_colour_plane, chroma_format_idc) {
+                    (false, 1) => (2, 2),
+                    (false, 2) => (2, 1),
+                    _          => (1, 1),
+                };
+            let (left, right) = (r.ue()? as u64, r.ue()? as u64);
+            let (top, bottom) = (r.ue()? as u64, r.ue()? as u64);
+            let crop_x = sub_width * (left + right);
+            let crop_y = sub_height * fields as u64 * (top + bottom);
+
+            width = (width as u64).checked_sub(crop_x)? as u32;
+            height = (height as u64).checked_sub(crop_y)? as u32;
+        }
+
+        let mut sar = None;
+        let mut overscan = None;
+        let mut full_range = false;
+        let mut matrix = None;
+        let mut timing = None;
+
+        if r.flag()? {
+            if r.flag()? {
+                sar = match r.bits(8)? {
+                    255 => Some((r.bits(16)? as u16, r.bits(16)? as u16)),
+                    idc => SAR.get(idc as usize).cloned(),
+                };
+            }
+            if r.flag()? {
+                overscan = Some(r.flag()?);
+            }
+            if r.flag()? {
+                r.bits(3)?; // video_format
+                full_range = r.flag()?;
+                if r.flag()? {
+                    r.bits(16)?; // colour_primaries, transfer_characteristics
+                    matrix = Some(r.bits(8)? as u8);
+                }
+            }
+            if r.flag()? {
+                r.ue()?;
+                r.ue()?;
+            }
+            if r.flag()? {
+                timing = Some((r.bits(32)?, r.bits(32)?));
+            }
+        }
+
+        Some(SpsInfo {
+            profile_idc,
+            constraint_flags,
+            level_idc,
+            sps_id,
+            chroma_format_idc,
+            bit_depth_luma,
+            bit_depth_chroma,
+            max_num_ref_frames,
+            frame_mbs_only,
+            width,
+            height,
+            sar,
+            overscan,
+            full_range,
+            matrix,
+            timing,
+        })
+    }
+}
+
+/// The sample aspect ratios that `aspect_ratio_idc` stands for, where 0
+/// means unspecified.
+const SAR: [(u16, u16); 17] = [
+    (0, 0), (1, 1), (12, 11), (10, 11), (16, 11), (40, 33), (24, 11),
+    (20, 11), (32, 11), (80, 33), (18, 11), (15, 11), (64, 33), (160, 99),
+    (4, 3), (3, 2), (2, 1),
+];
+
+fn strip_start_code(unit: &[u8]) -> &[u8] {
+    match unit {
+        [0, 0, 0, 1, rest @ ..] | [0, 0, 1, rest @ ..] => rest,
+        _ => unit,
+    }
+}
+
+/// Reads bits from a NAL unit, skipping emulation prevention bytes.
+struct BitReader<'a> {
+    data: &'a [u8],
+    byte: usize,
+    bit: u32,
+    zeros: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, byte: 0, bit: 0, zeros: 0 }
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        if self.bit == 0 {
+            // A 3 after two zero bytes is only there to escape start codes.
+            if self.zeros >= 2 && *self.data.get(self.byte)? == 3 {
+                self.byte += 1;
+                self.zeros = 0;
+            }
+            let byte = *self.data.get(self.byte)?;
+            self.zeros = if byte == 0 { self.zeros + 1 } else { 0 };
+        }
+
+        let byte = self.data[self.byte];
+        let set = byte >> (7 - self.bit) & 1 != 0;
+
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.byte += 1;
+        }
+        Some(set)
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..n {
+            value = value << 1 | self.flag()? as u32;
+        }
+        Some(value)
+    }
+
+    fn ue(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while !self.flag()? {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        Some((1 << zeros) - 1 + self.bits(zeros)?)
+    }
+
+    fn se(&mut self) -> Option<i32> {
+        let k = self.ue()? as i64;
+        Some(if k % 2 == 1 { k / 2 + 1 } else { -(k / 2) } as i32)
+    }
+
+    fn scaling_list(&mut self, size: usize) -> Option<()> {
+        let (mut last, mut next) = (8, 8);
+        for _ in 0..size {
+            if next != 0 {
+                next = (last + self.se()? + 256) % 256;
+            }
+            if next != 0 {
+                last = next;
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// x264's SPS for 1920x1080 at 30 fps, whose timing needs escaping and
+    /// whose height is cropped from 1088.
+    const HD: [u8; 26] = [
+        0x67, 0x64, 0x00, 0x28, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5,
+        0x84, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf0,
+        0x3c, 0x60, 0xc6, 0x58,
+    ];
+
+    /// A 352x288 SPS with one custom scaling list and one that falls back
+    /// to the default.
+    const SCALING: [u8; 19] = [
+        0x67, 0x64, 0x00, 0x1e, 0xad, 0x84, 0x12, 0x49, 0x24, 0x92, 0x49,
+        0x24, 0x08, 0x45, 0xb2, 0x02, 0xc1, 0x2c, 0x80,
+    ];
+
+    fn framed(annexb: bool) -> [u8; 30] {
+        let mut unit = [0; 30];
+        let prefix = if annexb { 1 } else { HD.len() as u8 };
+        unit[..4].copy_from_slice(&[0, 0, 0, prefix]);
+        unit[4..].copy_from_slice(&HD);
+        unit
+    }
+
+    #[test]
+    fn escaped_and_cropped() {
+        let sps = SpsInfo::parse(&HD).unwrap();
+        assert_eq!(sps.profile_idc, 100);
+        assert_eq!(sps.level_idc, 40);
+        assert_eq!(sps.chroma_format_idc, 1);
+        assert_eq!(sps.max_num_ref_frames, 4);
+        assert!(sps.frame_mbs_only);
+        assert_eq!((sps.width, sps.height), (1920, 1080));
+        assert_eq!(sps.sar, None);
+        assert_eq!(sps.overscan, None);
+        assert_eq!(sps.matrix, None);
+        assert_eq!(sps.timing, Some((1, 60)));
+    }
+
+    #[test]
+    fn scaling_matrix() {
+        let sps = SpsInfo::parse(&SCALING).unwrap();
+        assert_eq!(sps.profile_idc, 100);
+        assert_eq!(sps.level_idc, 30);
+        assert_eq!(sps.max_num_ref_frames, 3);
+        assert_eq!((sps.width, sps.height), (352, 288));
+        assert_eq!(sps.timing, None);
+    }
+
+    #[test]
+    fn framing() {
+        // What `Encoder::sps_info` cuts off for each framing.
+        for &(annexb, prefix) in &[(true, 0), (false, 4)] {
+            let unit = framed(annexb);
+            let sps = SpsInfo::parse(&unit[prefix..]);
+            assert_eq!(sps, SpsInfo::parse(&HD));
+        }
+
+        assert_eq!(SpsInfo::parse(&framed(true)[1..]), SpsInfo::parse(&HD));
+        assert_eq!(SpsInfo::parse(&framed(false)), None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(SpsInfo::parse(&[]), None);
+        assert_eq!(SpsInfo::parse(&[0x68, 0xeb, 0xe3, 0xcb]), None);
+        assert_eq!(SpsInfo::parse(&HD[..8]), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Synthetic frames, for trying out a pipeline without a real source.
+//!
+//! The patterns are described in 8-bit limited-range YUV, and converted
+//! with the BT.601 matrix for RGB input. They are deterministic, so the same
+//! frame number always gives the same image.
+
+use {Colorspace, Encoding, Modifier, OwnedImage};
+
+/// The 75% color bars, from white to black, as Y, U and V.
+const BARS: [[u8; 3]; 8] = [
+    [180, 128, 128],
+    [162,  44, 142],
+    [131, 156,  44],
+    [112,  72,  58],
+    [ 84, 184, 198],
+    [ 65, 100, 212],
+    [ 35, 212, 114],
+    [ 16, 128, 128],
+];
+
+/// An image of a single color, given as Y, U and V.
+///
+/// # Panics
+///
+/// Panics like `plane_sizes`, or if the encoding is high depth or V210.
+pub fn solid<E: Into<Encoding>>(
+    format: E,
+    width:  i32,
+    height: i32,
+    yuv:    [u8; 3],
+) -> OwnedImage {
+    render(format.into(), width, height, |_, _| yuv)
+}
+
+/// A gray ramp from black on the left to white on the right, which moves 4
+/// pixels to the left every frame, wrapping around.
+///
+/// # Panics
+///
+/// Panics like `plane_sizes`, or if the encoding is high depth or V210.
+pub fn gradient<E: Into<Encoding>>(
+    format: E,
+    width:  i32,
+    height: i32,
+    frame:  u32,
+) -> OwnedImage {
+    let shift = scroll(width, frame);
+    let span = (width - 1).max(1);
+
+    render(format.into(), width, height, |x, _| {
+        let x = (x + shift) % width;
+        [(16 + x * 219 / span) as u8, 128, 128]
+    })
+}
+
+/// Eight vertical color bars, which move 4 pixels to the left every frame,
+/// wrapping around.
+///
+/// # Panics
+///
+/// Panics like `plane_sizes`, or if the encoding is high depth or V210.
+pub fn bars<E: Into<Encoding>>(
+    format: E,
+    width:  i32,
+    height: i32,
+    frame:  u32,
+) -> OwnedImage {
+    let shift = scroll(width, frame);
+
+    render(format.into(), width, height, |x, _| {
+        BARS[((x + shift) % width * 8 / width) as usize]
+    })
+}
+
+fn scroll(width: i32, frame: u32) -> i32 {
+    (frame as i64 * 4 % width.max(1) as i64) as i32
+}
+
+fn render<F>(format: Encoding, width: i32, height: i32, pixel: F)
+    -> OwnedImage
+where
+    F: Fn(i32, i32) -> [u8; 3],
+{
+    use self::Colorspace::*;
+
+    assert!(!format.has(Modifier::HighDepth));
+
+    let mut image = OwnedImage::new(format, width, height);
+
+    match format.colorspace() {
+        BGR  => rgb(&mut image, 3, [2, 1, 0], width, height, pixel),
+        BGRA => rgb(&mut image, 4, [2, 1, 0], width, height, pixel),
+        RGB  => rgb(&mut image, 3, [0, 1, 2], width, height, pixel),
+        #[cfg(feature = "yuyv")]
+        csp @ YUYV | csp @ UYVY => {
+            packed(&mut image, csp == UYVY, width, height, pixel)
+        }
+        V210 => panic!("V210 test patterns aren't supported"),
+        csp  => planar(&mut image, csp, width, height, pixel),
+    }
+
+    image
+}
+
+fn rgb<F>(
+    image: &mut OwnedImage,
+    bpp: usize,
+    order: [usize; 3],
+    width: i32,
+    height: i32,
+    pixel: F,
+) where
+    F: Fn(i32, i32) -> [u8; 3],
+{
+    let stride = image.stride(0) as usize;
+    let plane = image.plane_mut(0);
+    for y in 0..height {
+        for x in 0..width {
+            let rgb = to_rgb(pixel(x, y));
+            let at = y as usize * stride + x as usize * bpp;
+            for (i, &c) in order.iter().enumerate() {
+                plane[at + i] = rgb[c];
+            }
+        }
+    }
+}
+
+#[cfg(feature = "yuyv")]
+fn packed<F>(
+    image: &mut OwnedImage,
+    chroma_first: bool,
+    width: i32,
+    height: i32,
+    pixel: F,
+) where
+    F: Fn(i32, i32) -> [u8; 3],
+{
+    let stride = image.stride(0) as usize;
+    let plane = image.plane_mut(0);
+    for y in 0..height {
+        for x in (0..width).step_by(2) {
+            let [y0, u, v] = pixel(x, y);
+            let y1 = pixel(x + 1, y)[0];
+            let at = y as usize * stride + x as usize * 2;
+            let bytes = if chroma_first {
+                [u, y0, v, y1]
+            } else {
+                [y0, u, y1, v]
+            };
+            plane[at..at + 4].copy_from_slice(&bytes);
+        }
+    }
+}
+
+fn planar<F>(
+    image: &mut OwnedImage,
+    csp: Colorspace,
+    width: i32,
+    height: i32,
+    pixel: F,
+) where
+    F: Fn(i32, i32) -> [u8; 3],
+{
+    use self::Colorspace::*;
+
+    let stride = image.stride(0) as usize;
+    let luma = image.plane_mut(0);
+    for y in 0..height {
+        for x in 0..width {
+            luma[y as usize * stride + x as usize] = pixel(x, y)[0];
+        }
+    }
+
+    let (ws, hs) = match csp {
+        I400                      => return,
+        I420 | YV12 | NV12 | NV21 => (2, 2),
+        I422 | YV16 | NV16        => (2, 1),
+        _                         => (1, 1),
+    };
+    let swap = match csp { YV12 | YV16 | YV24 | NV21 => 1, _ => 0 };
+    let semi = matches!(csp, NV12 | NV21 | NV16);
+
+    // Chroma is taken from the top left pixel of each block.
+    for c in 0..2 {
+        let (p, step, offset) = if semi { (1, 2, c) } else { (1 + c, 1, 0) };
+        let stride = image.stride(p) as usize;
+        let plane = image.plane_mut(p);
+        for y in 0..height / hs {
+            for x in 0..width / ws {
+                let value = pixel(x * ws, y * hs)[1 + (c ^ swap)];
+                plane[y as usize * stride + x as usize * step + offset] = value;
+            }
+        }
+    }
+}
+
+/// Converts limited-range BT.601 YUV to full-range RGB.
+fn to_rgb([y, u, v]: [u8; 3]) -> [u8; 3] {
+    let c = 298 * (y as i32 - 16);
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clip = |x: i32| ((x + 128) >> 8).clamp(0, 255) as u8;
+
+    [clip(c + 409 * e), clip(c - 100 * d - 208 * e), clip(c + 516 * d)]
+}
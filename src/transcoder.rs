@@ -0,0 +1,126 @@
+use {DtsAdjuster, Encoder, Image, KeyframeIndex};
+use std::io;
+
+/// Encodes frames straight into a writer, such as a file, taking care of
+/// the timestamps, the headers and the flushing.
+///
+/// This is only a thin layer over `Encoder`, which stays available through
+/// `encoder_mut`. The output is framed as `Setup::annexb` says, so it is a
+/// playable Annex B stream by default. The frames are taken to come at a
+/// constant framerate, so this doesn't suit `Setup::telecine` or variable
+/// framerate input.
+pub struct Transcoder<W> {
+    encoder: Encoder,
+    out: W,
+    dts: DtsAdjuster,
+    index: KeyframeIndex,
+    ticks: (u128, u128),
+    frames_in: u64,
+    frames_out: u64,
+    headers: bool,
+}
+
+impl<W: io::Write> Transcoder<W> {
+    /// Wraps an encoder, to write everything it outputs into `out`.
+    pub fn new(encoder: Encoder, out: W) -> Self {
+        let params = encoder.effective_params();
+        let (fps_num, fps_den) = params.fps();
+        let (tb_num, tb_den) = params.timebase();
+
+        Transcoder {
+            ticks: (
+                fps_den as u128 * tb_den as u128,
+                fps_num as u128 * tb_num as u128,
+            ),
+            headers: params.raw().b_repeat_headers == 0,
+            encoder,
+            out,
+            dts: DtsAdjuster::new(),
+            index: KeyframeIndex::new(),
+            frames_in: 0,
+            frames_out: 0,
+        }
+    }
+
+    /// Encodes the next frame, timestamping it from the framerate.
+    ///
+    /// This gives the presentation and decoding timestamps of the frame that
+    /// was written, shifted as `DtsAdjuster` does, or `None` while the
+    /// encoder is holding frames back. Encoder failures, including images
+    /// that don't match the encoder, come back as `io::ErrorKind::Other`,
+    /// wrapping an `Error`.
+    pub fn push(&mut self, image: Image) -> io::Result<Option<(i64, i64)>> {
+        self.write_headers()?;
+
+        let (num, den) = self.ticks;
+        let pts = (self.frames_in as u128 * num / den) as i64;
+        self.frames_in += 1;
+
+        let (data, picture) = self.encoder
+            .try_encode(pts, image)
+            .map_err(io::Error::other)?;
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        self.out.write_all(data.entirety())?;
+        self.index.record(&data, &picture);
+        self.frames_out += 1;
+        Ok(Some(self.dts.adjust(&picture)))
+    }
+
+    /// Flushes the delayed frames into the writer, and gives it back.
+    pub fn finish(self) -> io::Result<W> {
+        self.finish_with_index().map(|(out, _)| out)
+    }
+
+    /// Flushes the delayed frames into the writer, and gives it back along
+    /// with the index of the keyframes that were written.
+    pub fn finish_with_index(mut self) -> io::Result<(W, KeyframeIndex)> {
+        self.write_headers()?;
+
+        let mut flush = self.encoder.flush();
+        while let Some(result) = flush.next() {
+            let (data, picture) = result.map_err(io::Error::other)?;
+            self.out.write_all(data.entirety())?;
+            self.index.record(&data, &picture);
+        }
+
+        Ok((self.out, self.index))
+    }
+
+    /// The number of frames that have been written so far, not counting the
+    /// ones still held back.
+    pub fn frames_written(&self) -> u64 {
+        self.frames_out
+    }
+
+    /// Where the keyframes written so far start in the output.
+    ///
+    /// The offsets count from the first byte that this wrote, headers
+    /// included.
+    pub fn keyframe_index(&self) -> &KeyframeIndex {
+        &self.index
+    }
+
+    /// The encoder.
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    /// The encoder, to change it or to call it directly.
+    pub fn encoder_mut(&mut self) -> &mut Encoder {
+        &mut self.encoder
+    }
+
+    fn write_headers(&mut self) -> io::Result<()> {
+        if self.headers {
+            let headers = self.encoder.headers().map_err(io::Error::other)?;
+            self.out.write_all(headers.entirety())?;
+            self.index.skip(headers.entirety().len() as u64);
+            self.headers = false;
+        }
+        Ok(())
+    }
+}
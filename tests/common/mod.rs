@@ -0,0 +1,160 @@
+//! Input and encoding loops shared by the tests.
+
+#![allow(dead_code)]
+
+use x264::*;
+
+/// The size of the frames that most tests encode.
+pub const WIDTH: i32 = 64;
+/// The size of the frames that most tests encode.
+pub const HEIGHT: i32 = 48;
+
+/// The `n`th I420 frame of a textured gradient that moves a little every
+/// frame, so that every frame type gets used.
+pub fn frame(width: i32, height: i32, n: u32) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    let n = n as usize;
+    let mut buf = vec![128; w * h * 3 / 2];
+
+    for y in 0..h {
+        for x in 0..w {
+            let texture = (x * 7 + y * 13 + x * y) % 32;
+            buf[y * w + x] = (16 + (x + n * 2) % w * 160 / w + texture) as u8;
+        }
+    }
+    for (i, c) in buf[w * h..].iter_mut().enumerate() {
+        *c = (112 + (i + n) % 32) as u8;
+    }
+
+    buf
+}
+
+/// Wraps an I420 frame from `frame`.
+pub fn image<'a>(buf: &'a [u8], width: i32, height: i32) -> Image<'a> {
+    let luma = (width * height) as usize;
+    let chroma = luma / 4;
+
+    Image::new(Colorspace::I420, width, height, &[
+        Plane { stride: width, data: &buf[..luma] },
+        Plane { stride: width / 2, data: &buf[luma..luma + chroma] },
+        Plane { stride: width / 2, data: &buf[luma + chroma..] },
+    ])
+}
+
+/// A fast setup that outputs every frame straight away.
+pub fn fast() -> Setup {
+    Setup::preset(Preset::Ultrafast, Tune::None, false, true)
+}
+
+/// Encodes `frames` frames from `frame`, with the frame number as the PTS,
+/// flushes the encoder, and calls `output` on everything that comes out.
+pub fn encode<F>(mut encoder: Encoder, frames: u32, mut output: F)
+where
+    F: FnMut(&Data, &Picture),
+{
+    let (width, height) = (encoder.width(), encoder.height());
+
+    for n in 0..frames {
+        let buf = frame(width, height, n);
+        let (data, picture) = encoder
+            .encode(n as i64, image(&buf, width, height))
+            .unwrap();
+        if !data.is_empty() {
+            output(&data, &picture);
+        }
+    }
+
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        let (data, picture) = result.unwrap();
+        output(&data, &picture);
+    }
+}
+
+/// Encodes like `encode`, and gives back the pictures' sizes, types and
+/// timestamps, in output order.
+pub fn pictures(encoder: Encoder, frames: u32) -> Vec<(usize, FrameType, i64)> {
+    let mut out = Vec::new();
+    encode(encoder, frames, |_, picture| {
+        out.push((picture.size_bytes(), picture.frame_type(), picture.pts()));
+    });
+    out
+}
+
+/// Encodes like `encode`, and gives back the total size of the output.
+pub fn size(encoder: Encoder, frames: u32) -> usize {
+    let mut size = 0;
+    encode(encoder, frames, |data, _| size += data.entirety().len());
+    size
+}
+
+/// The unit without its start code or length prefix.
+pub fn nal(unit: &[u8]) -> &[u8] {
+    match unit {
+        [0, 0, 1, rest @ ..] => rest,
+        [_, _, _, _, rest @ ..] => rest,
+        _ => panic!("unit too short"),
+    }
+}
+
+/// The bytes of a unit without its emulation prevention bytes.
+pub fn unescape(nal: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        out.push(byte);
+    }
+    out
+}
+
+/// The payload types and payloads of the messages in an SEI unit.
+pub fn sei_messages(unit: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    let rbsp = unescape(nal(unit));
+    let mut rest = &rbsp[1..];
+    let mut messages = Vec::new();
+
+    // The last byte is the stop bit.
+    while rest.len() > 1 {
+        let mut read = || {
+            let mut value = 0;
+            while rest[0] == 0xFF {
+                value += 255;
+                rest = &rest[1..];
+            }
+            value += rest[0] as u32;
+            rest = &rest[1..];
+            value
+        };
+        let kind = read();
+        let size = read() as usize;
+        messages.push((kind, rest[..size].to_vec()));
+        rest = &rest[size..];
+    }
+
+    messages
+}
+
+/// The SEI messages of the given type in some output.
+pub fn sei(data: &Data, kind: u32) -> Vec<Vec<u8>> {
+    data.units()
+        .filter(|unit| unit.unit_type() == NalUnitType::Sei)
+        .flat_map(|unit| sei_messages(unit.as_ref()))
+        .filter(|&(k, _)| k == kind)
+        .map(|(_, payload)| payload)
+        .collect()
+}
+
+/// The number of slices in some output.
+pub fn slices(data: &Data) -> usize {
+    data.units()
+        .filter(|unit| {
+            let kind = unit.unit_type();
+            kind == NalUnitType::Slice || kind == NalUnitType::SliceIdr
+        })
+        .count()
+}
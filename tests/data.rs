@@ -0,0 +1,179 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+#[test]
+fn slice_max_mbs_splits_frames() {
+    // 64x48 is 12 macroblocks.
+    let encoder = common::fast()
+        .slice_max_mbs(4)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut frames = 0;
+    common::encode(encoder, 3, |data, _| {
+        assert_eq!(common::slices(data), 3);
+        frames += 1;
+    });
+    assert_eq!(frames, 3);
+}
+
+#[test]
+fn slice_counts() {
+    let slices = |setup: Setup| {
+        let encoder = setup.build(Colorspace::I420, 64, 128).unwrap();
+        let mut counts = Vec::new();
+        common::encode(encoder, 5, |data, _| {
+            counts.push(common::slices(data));
+        });
+        counts
+    };
+
+    assert_eq!(slices(common::fast().slices(4)), [4; 5]);
+
+    let uncapped = slices(common::fast().slice_max_size(100));
+    assert!(uncapped.iter().all(|&count| count > 3), "{:?}", uncapped);
+    let capped = common::fast().slice_max_size(100).slice_count_max(3);
+    assert_eq!(slices(capped), [3; 5]);
+}
+
+#[test]
+fn headers_follow_annexb() {
+    use NalUnitType::*;
+
+    let headers = |annexb| {
+        let mut encoder = common::fast()
+            .annexb(annexb)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        assert_eq!(encoder.sps_info().unwrap().width, 64);
+
+        let headers = encoder.headers().unwrap();
+        let kinds: Vec<_> =
+            headers.units().map(|unit| unit.unit_type()).collect();
+        assert_eq!(kinds, [Sps, Pps, Sei]);
+        (headers.entirety().to_vec(), kinds.len())
+    };
+
+    let (bytes, _) = headers(true);
+    assert_eq!(bytes[..5], [0, 0, 0, 1, 0x67]);
+
+    // Without Annex-B, each unit has a four byte big-endian length instead.
+    let (bytes, units) = headers(false);
+    let (mut offset, mut count) = (0, 0);
+    while offset < bytes.len() {
+        let mut len = [0; 4];
+        len.copy_from_slice(&bytes[offset..offset + 4]);
+        if count == 0 {
+            assert_eq!(bytes[offset + 4], 0x67);
+        }
+        offset += 4 + u32::from_be_bytes(len) as usize;
+        count += 1;
+    }
+    assert_eq!(offset, bytes.len());
+    assert_eq!(count, units);
+}
+
+#[test]
+fn b_frames_are_droppable() {
+    let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .b_pyramid(BPyramid::None)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut seen = Vec::new();
+    common::encode(encoder, 20, |data, picture| {
+        for unit in data.units() {
+            let header = common::nal(unit.as_ref())[0];
+            assert_eq!(unit.ref_idc(), header >> 5 & 3);
+
+            let kind = unit.unit_type();
+            if kind == NalUnitType::Slice || kind == NalUnitType::SliceIdr {
+                seen.push((picture.frame_type(), unit.ref_idc()));
+            }
+        }
+    });
+
+    assert!(seen.contains(&(FrameType::B, 0)));
+    for &(kind, ref_idc) in &seen {
+        assert_eq!(ref_idc == 0, kind == FrameType::B, "{:?}", kind);
+    }
+}
+
+#[test]
+fn uniform_slices_start_on_even_rows() {
+    // 64x96 is 4 macroblocks wide and 6 high, so 3 slices take 2 rows each.
+    let encoder = common::fast()
+        .threads(1)
+        .slice_max_size(200)
+        .uniform_slices(3)
+        .build(Colorspace::I420, common::WIDTH, 96)
+        .unwrap();
+
+    let mut frames = 0;
+    common::encode(encoder, 10, |data, _| {
+        let firsts: Vec<u32> = data
+            .units()
+            .filter(|unit| {
+                let kind = unit.unit_type();
+                kind == NalUnitType::Slice || kind == NalUnitType::SliceIdr
+            })
+            .map(|unit| first_mb(unit.as_ref()))
+            .collect();
+        assert_eq!(firsts, [0, 8, 16]);
+        frames += 1;
+    });
+    assert_eq!(frames, 10);
+}
+
+/// Reads `first_mb_in_slice`, the Exp-Golomb number after a slice's header.
+fn first_mb(unit: &[u8]) -> u32 {
+    let payload = common::unescape(common::nal(unit));
+    let bit = |i: usize| (payload[1 + i / 8] >> (7 - i % 8)) & 1;
+    let mut zeros = 0;
+    while bit(zeros) == 0 {
+        zeros += 1;
+    }
+    let mut value = 1;
+    for i in 0..zeros {
+        value = value << 1 | bit(zeros + 1 + i) as u32;
+    }
+    value - 1
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn filler_can_be_left_out() {
+    // Frames this small at 2 Mbps need padding out to the bitrate.
+    let encoder = common::fast()
+        .live_cbr(BitRate::kbps(2000), 30, 1)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut filler = 0;
+    common::encode(encoder, 30, |data, _| {
+        let kept: Vec<Vec<u8>> = data
+            .units()
+            .filter(|unit| unit.unit_type() != NalUnitType::Filler)
+            .map(|unit| unit.as_ref().to_vec())
+            .collect();
+        filler += data.len() - kept.len();
+
+        let without: Vec<Vec<u8>> = data
+            .units_without_filler()
+            .map(|unit| unit.as_ref().to_vec())
+            .collect();
+        assert_eq!(without, kept);
+
+        let buf = DataBuf::from(data);
+        let without: Vec<Vec<u8>> = buf
+            .units_without_filler()
+            .map(|unit| unit.as_ref().to_vec())
+            .collect();
+        assert_eq!(without, kept);
+    });
+    assert!(filler > 0);
+}
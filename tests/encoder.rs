@@ -0,0 +1,936 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+/// How many frames go in before the first one comes out.
+fn delay(mut encoder: Encoder) -> u32 {
+    let (width, height) = (encoder.width(), encoder.height());
+
+    for n in 0..100 {
+        let buf = common::frame(width, height, n);
+        let (data, _) = encoder
+            .encode(n as i64, common::image(&buf, width, height))
+            .unwrap();
+        if !data.is_empty() {
+            return n;
+        }
+    }
+    panic!("nothing came out");
+}
+
+#[test]
+fn sliced_threads_have_no_delay() {
+    let build = |sliced| {
+        common::fast()
+            .threads(4)
+            .sliced_threads(sliced)
+            .build(Colorspace::I420, 128, 128)
+            .unwrap()
+    };
+
+    assert_eq!(delay(build(false)), 3);
+    assert_eq!(delay(build(true)), 0);
+}
+
+#[test]
+fn intra_refresh_sweeps_once_per_period() {
+    let encoder = common::fast()
+        .intra_refresh_period(10)
+        .build(Colorspace::I420, 320, 48)
+        .unwrap();
+
+    let mut n = 0;
+    common::encode(encoder, 40, |_, picture| {
+        let expected = if n == 0 { FrameType::Idr } else { FrameType::P };
+        assert_eq!(picture.frame_type(), expected);
+        assert_eq!(picture.keyframe(), n % 10 == 0);
+        assert_eq!(picture.is_recovery_point(), n > 0 && n % 10 == 0);
+        n += 1;
+    });
+    assert_eq!(n, 40);
+}
+
+#[test]
+fn forced_qp_is_used() {
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+
+    let mut qps = Vec::new();
+    for n in 0..6 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (_, picture) = if n == 3 {
+            encoder.encode_with_qp(n as i64, image, 5).unwrap()
+        } else {
+            encoder.encode(n as i64, image).unwrap()
+        };
+        qps.push(picture.qp());
+    }
+
+    assert_eq!(qps[3], 5);
+    assert!(qps.iter().enumerate().all(|(n, &qp)| n == 3 || qp > 5));
+}
+
+#[test]
+fn smaller_deadzones_cost_more() {
+    let size = |deadzone| {
+        let encoder = common::fast()
+            .deadzone(deadzone, deadzone)
+            .build(Colorspace::I420, 128, 128)
+            .unwrap();
+        common::size(encoder, 5)
+    };
+
+    let (fine, coarse) = (size(0), size(32));
+    assert!(fine > coarse, "{} <= {}", fine, coarse);
+}
+
+#[test]
+fn chroma_me_changes_the_output() {
+    let size = |enabled| {
+        let encoder = Setup::preset(Preset::Fast, Tune::None, false, true)
+            .chroma_me(enabled)
+            .build(Colorspace::I420, 128, 128)
+            .unwrap();
+        assert_eq!(encoder.effective_params().raw().analyse.b_chroma_me,
+            enabled as i32);
+        common::size(encoder, 8)
+    };
+
+    assert_ne!(size(true), size(false));
+}
+
+#[test]
+fn refresh_only_on_demand() {
+    let mut encoder = common::fast()
+        .feedback_driven_refresh()
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+
+    let mut keyframes = Vec::new();
+    for n in 0..300 {
+        // The picture jumps every 50 frames.
+        let buf = common::frame(width, height, n / 50 * 97);
+        if n == 200 {
+            encoder.intra_refresh();
+        }
+        let (_, picture) = encoder
+            .encode(n as i64, common::image(&buf, width, height))
+            .unwrap();
+        if picture.keyframe() {
+            keyframes.push((picture.pts(), picture.frame_type()));
+        }
+    }
+
+    assert_eq!(keyframes, [(0, FrameType::Idr), (200, FrameType::P)]);
+}
+
+/// A frame of noise, which is the same for a scene apart from a little
+/// brightening each frame, so that every new scene is a cut.
+fn scene(scene: u32, n: u32) -> Vec<u8> {
+    let mut x = scene.wrapping_mul(2_654_435_761) | 1;
+    let size = (common::WIDTH * common::HEIGHT * 3 / 2) as u32;
+    (0..size)
+        .map(|i| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            ((x >> 24) + n + i % 7) as u8
+        })
+        .collect()
+}
+
+/// The timestamps and types of the I and IDR frames, in display order,
+/// from two scene cuts 5 frames apart.
+fn scene_cuts(keyint_min: u32) -> Vec<(i64, FrameType, bool)> {
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .keyint_min(keyint_min)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut out = Vec::new();
+    let mut record = |picture: &Picture| {
+        let kind = picture.frame_type();
+        if kind == FrameType::I || kind == FrameType::Idr {
+            out.push((picture.pts(), kind, picture.keyframe()));
+        }
+    };
+
+    for n in 0..60 {
+        let buf = scene(if n < 20 { 0 } else if n < 25 { 1 } else { 2 }, n);
+        let image = common::image(&buf, common::WIDTH, common::HEIGHT);
+        let (data, picture) = encoder.encode(n as i64, image).unwrap();
+        if !data.is_empty() {
+            record(&picture);
+        }
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        record(&result.unwrap().1);
+    }
+
+    out.sort_by_key(|&(pts, _, _)| pts);
+    out
+}
+
+#[test]
+fn keyint_min_holds_back_scene_cuts() {
+    use FrameType::*;
+
+    assert_eq!(scene_cuts(1),
+        [(0, Idr, true), (20, Idr, true), (25, Idr, true)]);
+    assert_eq!(scene_cuts(30),
+        [(0, Idr, true), (20, I, false), (25, I, false)]);
+}
+
+#[test]
+fn dpb_size_matches_the_sps() {
+    let medium = || Setup::preset(Preset::Medium, Tune::None, false, false);
+    let cases = vec![
+        (medium().ref_frames(5), 5),
+        (medium().ref_frames(1), 4),
+        (medium().ref_frames(1).b_pyramid(BPyramid::None), 2),
+        (medium().ref_frames(1).dpb_size(9), 9),
+        (common::fast().ref_frames(1), 1),
+        (common::fast().ref_frames(16).dpb_size(20), 16),
+    ];
+
+    for (setup, expected) in cases {
+        let mut encoder = setup
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        assert_eq!(encoder.dpb_size(), expected);
+        let sps = encoder.sps_info().unwrap();
+        assert_eq!(sps.max_num_ref_frames, expected);
+    }
+}
+
+#[test]
+fn benchmark_mode_is_deterministic() {
+    let build = |size| {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+            .benchmark_mode(4)
+            .build(Colorspace::I420, size, size)
+            .unwrap()
+    };
+
+    // At least 4 rows of macroblocks per thread.
+    assert_eq!(build(256).thread_count(), 4);
+    assert_eq!(build(128).thread_count(), 2);
+
+    let single = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, 256, 256)
+        .unwrap();
+    assert_eq!(delay(build(256)), delay(single));
+
+    let run = || {
+        let encoder = build(256);
+        let mut out = Vec::new();
+        common::encode(encoder, 20, |data, _| {
+            out.extend_from_slice(data.entirety());
+        });
+        out
+    };
+
+    let first = run();
+    assert!(!first.is_empty());
+    assert_eq!(first, run());
+}
+
+#[test]
+fn reopen_starts_with_an_idr() {
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let encode = |encoder: &mut Encoder, n| {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, picture) = encoder.encode(n as i64, image).unwrap();
+        (data.is_empty(), picture.frame_type())
+    };
+
+    // Lookahead holds all of these back, and reopening drops them.
+    for n in 0..5 {
+        assert!(encode(&mut encoder, n).0);
+    }
+    encoder.reopen().unwrap();
+
+    let mut out = Vec::new();
+    for n in 0..100 {
+        let (empty, kind) = encode(&mut encoder, n);
+        if !empty {
+            out.push(kind);
+        }
+    }
+    assert_eq!(out[0], FrameType::Idr);
+    assert_eq!(common::pictures(encoder, 0).len(), 100 - out.len());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn avc_intra_is_all_idr() {
+    let classes = [
+        (AvcIntraClass::Class50, Colorspace::I420, 960, 720),
+        (AvcIntraClass::Class100, Colorspace::I422, 1280, 720),
+        (AvcIntraClass::Class200, Colorspace::I422, 1280, 720),
+    ];
+
+    for &(class, csp, width, height) in &classes {
+        let format = Encoding::from(csp).add(Modifier::HighDepth);
+        let setup = Setup::preset(Preset::Ultrafast, Tune::None, false, false);
+        let mut encoder = setup
+            .fps(60_000, 1001)
+            .avc_intra(class)
+            .build(format, width, height)
+            .unwrap();
+        let image = OwnedImage::new(format, width, height);
+
+        let mut frames = 0;
+        for n in 0..3 {
+            let (data, picture) = encoder.encode(n, image.as_image()).unwrap();
+            if !data.is_empty() {
+                assert_eq!(picture.frame_type(), FrameType::Idr);
+                frames += 1;
+            }
+        }
+        let mut flush = encoder.flush();
+        while let Some(result) = flush.next() {
+            assert_eq!(result.unwrap().1.frame_type(), FrameType::Idr);
+            frames += 1;
+        }
+        assert_eq!(frames, 3, "{:?}", class);
+    }
+
+    let format = Encoding::from(Colorspace::I420).add(Modifier::HighDepth);
+    let setup = Setup::preset(Preset::Ultrafast, Tune::None, false, false)
+        .fps(60_000, 1001)
+        .avc_intra(AvcIntraClass::Class50);
+    assert_eq!(setup.build(format, 640, 480).err(), Some(Error::Failed));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn frame_type_pattern_is_followed() {
+    use FrameTypeHint as H;
+
+    let pattern = [
+        H::Idr, H::B, H::B, H::P, H::B, H::B, H::P, H::B, H::B, H::P,
+    ];
+    let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .b_pyramid(BPyramid::None)
+        .frame_type_pattern(&pattern)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut pictures = common::pictures(encoder, 30);
+    pictures.sort_by_key(|&(_, _, pts)| pts);
+    assert_eq!(pictures.len(), 30);
+
+    for (n, &(_, kind, _)) in pictures.iter().enumerate() {
+        let expected = match n % 10 {
+            0         => FrameType::Idr,
+            3 | 6 | 9 => FrameType::P,
+            _         => FrameType::B,
+        };
+        assert_eq!(kind, expected, "frame {}", n);
+    }
+}
+
+#[test]
+fn vfr_timestamps_keep_dts_in_order() {
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .vfr_input(true)
+        .timebase(1, 1000)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+
+    // Gaps of 20 to 100 milliseconds.
+    let pts: Vec<i64> = (0..30)
+        .scan(0, |pts, n| {
+            *pts += 20 + n * 37 % 81;
+            Some(*pts)
+        })
+        .collect();
+    let mut out = Vec::new();
+    for (n, &pts) in pts.iter().enumerate() {
+        let buf = common::frame(width, height, n as u32);
+        let (data, picture) = encoder
+            .encode(pts, common::image(&buf, width, height))
+            .unwrap();
+        if !data.is_empty() {
+            out.push((picture.pts(), picture.dts()));
+        }
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        let picture = result.unwrap().1;
+        out.push((picture.pts(), picture.dts()));
+    }
+
+    for pair in out.windows(2) {
+        assert!(pair[0].1 < pair[1].1, "{:?}", out);
+    }
+    for &(pts, dts) in &out {
+        assert!(dts <= pts, "{:?}", out);
+    }
+
+    let mut sorted: Vec<i64> = out.iter().map(|&(pts, _)| pts).collect();
+    sorted.sort();
+    assert_eq!(sorted, pts);
+}
+
+#[test]
+fn b_rdo_changes_the_b_frames() {
+    let b_frames = |rdo| {
+        let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .subme(5)
+            .b_rdo(rdo)
+            .build(Colorspace::I420, 128, 128)
+            .unwrap();
+        let subme = encoder.effective_params().raw().analyse.i_subpel_refine;
+        assert_eq!(subme, if rdo { 7 } else { 5 });
+
+        common::pictures(encoder, 30)
+            .into_iter()
+            .filter(|&(_, kind, _)| kind == FrameType::B)
+            .map(|(size, _, _)| size)
+            .collect::<Vec<_>>()
+    };
+
+    let (plain, rdo) = (b_frames(false), b_frames(true));
+    assert!(!plain.is_empty());
+    assert_ne!(plain, rdo);
+}
+
+#[test]
+fn debug_output() {
+    let setup = common::fast().bitrate(BitRate::kbps(800)).keyint(60);
+    let text = format!("{:?}", setup);
+    assert!(text.starts_with("Setup {"), "{}", text);
+    assert!(text.contains("rate_control: ConstantRateFactor"), "{}", text);
+    assert!(text.contains("bitrate: 800"), "{}", text);
+    assert!(text.contains("keyint: 60"), "{}", text);
+
+    let mut encoder = setup.build(Colorspace::I420, 64, 48).unwrap();
+    let text = format!("{:?}", encoder);
+    assert!(text.starts_with("Encoder {"), "{}", text);
+    assert!(text.contains("width: 64, height: 48"), "{}", text);
+    assert!(text.contains("colorspace: I420"), "{}", text);
+
+    let buf = common::frame(64, 48, 0);
+    let (_, picture) = encoder.encode(7, common::image(&buf, 64, 48)).unwrap();
+    let text = format!("{:?}", picture);
+    assert!(text.starts_with("Picture {"), "{}", text);
+    assert!(text.contains("frame_type: Idr"), "{}", text);
+    assert!(text.contains("pts: 7"), "{}", text);
+    assert!(text.contains("keyframe: true"), "{}", text);
+
+    let encoding = Encoding::from(Colorspace::NV12).add(Modifier::HighDepth);
+    assert_eq!(format!("{:?}", encoding),
+        "Encoding { colorspace: NV12, high_depth: true, \
+         vertical_flip: false }");
+}
+
+#[test]
+fn automatic_threads_read_back() {
+    let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(0)
+        .lookahead_threads(0)
+        .build(Colorspace::I420, 640, 480)
+        .unwrap();
+
+    let config = encoder.thread_config();
+    assert!(encoder.thread_count() >= 1);
+    assert_eq!(config.frame_threads, encoder.thread_count());
+    assert!(config.lookahead_threads >= 1);
+
+    let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, 640, 480)
+        .unwrap();
+    assert_eq!(encoder.thread_count(), 1);
+    assert_eq!(encoder.thread_config().lookahead_threads, 1);
+}
+
+#[test]
+fn try_encode_rejects_mismatched_images() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    let buf = common::frame(width, height, 0);
+    let mismatch = |field| Err(Error::ImageMismatch { field });
+
+    let wide = common::frame(width * 2, height, 0);
+    let result = encoder.try_encode(0, common::image(&wide, width * 2, height));
+    assert_eq!(result.map(|_| ()), mismatch(ImageField::Width));
+
+    let tall = common::frame(width, height * 2, 0);
+    let result = encoder.try_encode(0, common::image(&tall, width, height * 2));
+    assert_eq!(result.map(|_| ()), mismatch(ImageField::Height));
+
+    let (luma, chroma) = buf.split_at((width * height) as usize);
+    let nv12 = Image::new(Colorspace::NV12, width, height, &[
+        Plane { stride: width, data: luma },
+        Plane { stride: width, data: chroma },
+    ]);
+    let result = encoder.try_encode(0, nv12);
+    assert_eq!(result.map(|_| ()), mismatch(ImageField::Encoding));
+
+    let image = common::image(&buf, width, height);
+    assert!(encoder.try_encode(0, image).is_ok());
+}
+
+/// The sizes of the I-frames at the scene cuts of a clip encoded in ABR
+/// mode, at an I-frame to P-frame quantizer ratio. The bitrate is low
+/// enough that the cuts aren't already at the lowest quantizer.
+fn cut_sizes(ratio: f32) -> Vec<usize> {
+    let setup = common::fast()
+        .scenecut(40)
+        .bitrate(BitRate::kbps(50))
+        .ip_ratio(ratio);
+    let abr = RateControl::AverageBitrate as i32;
+    let mut encoder = unsafe {
+        setup.with_raw_params(|raw| raw.rc.i_rc_method = abr)
+    }
+    .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+    .unwrap();
+
+    let mut sizes = Vec::new();
+    for n in 0..60 {
+        let buf = scene(n / 20, n);
+        let image = common::image(&buf, common::WIDTH, common::HEIGHT);
+        let (_, picture) = encoder.encode(n as i64, image).unwrap();
+        let kind = picture.frame_type();
+        if n > 0 && (kind == FrameType::I || kind == FrameType::Idr) {
+            sizes.push(picture.size_bytes());
+        }
+    }
+    sizes
+}
+
+#[test]
+fn ip_ratio_boosts_scene_cuts() {
+    let (plain, boosted) = (cut_sizes(1.0), cut_sizes(3.0));
+
+    assert_eq!(plain.len(), 2);
+    assert_eq!(boosted.len(), 2);
+    for (plain, boosted) in plain.iter().zip(&boosted) {
+        assert!(boosted > plain, "{} <= {}", boosted, plain);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn encode_timed_takes_time() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let buf = common::frame(width, height, 0);
+    let image = common::image(&buf, width, height);
+    let (data, picture, time) = encoder.encode_timed(0, image).unwrap();
+
+    assert!(!data.is_empty());
+    assert_eq!(picture.frame_type(), FrameType::Idr);
+    assert!(time.as_nanos() > 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn flush_into_writes_the_delayed_frames() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let build = || {
+        let setup = Setup::preset(Preset::Medium, Tune::None, false, false);
+        let mut encoder = setup
+            .threads(1)
+            .build(Colorspace::I420, width, height)
+            .unwrap();
+        for n in 0..30 {
+            let buf = common::frame(width, height, n);
+            let image = common::image(&buf, width, height);
+            encoder.encode(n as i64, image).unwrap();
+        }
+        encoder
+    };
+
+    let mut written = Vec::new();
+    build().flush_into(&mut written).unwrap();
+
+    let mut collected = Vec::new();
+    let mut flush = build().flush();
+    while let Some(result) = flush.next() {
+        collected.extend_from_slice(result.unwrap().0.entirety());
+    }
+
+    assert!(!collected.is_empty());
+    assert_eq!(written, collected);
+}
+
+/// The squared error of the reconstructed luma over some frames, at a
+/// constant QP, with or without the psychovisual optimizations.
+fn luma_error(psy: bool) -> u64 {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let setup = Setup::preset(Preset::Medium, Tune::None, false, true)
+        .threads(1)
+        .full_recon(true);
+    let setup = if psy { setup } else { setup.disable_psy() };
+    let cqp = RateControl::ConstantQp as i32;
+    let mut encoder = unsafe {
+        setup.with_raw_params(|raw| {
+            raw.rc.i_rc_method = cqp;
+            raw.rc.i_qp_constant = 30;
+        })
+    }
+    .build(Colorspace::I420, width, height)
+    .unwrap();
+
+    let mut error = 0;
+    for n in 0..10 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        encoder.encode(n as i64, image).unwrap();
+
+        let recon = encoder.reconstructed().unwrap();
+        let (luma, stride) = (recon.plane(0), recon.stride(0) as usize);
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let source = buf[y * width as usize + x] as i64;
+                let diff = source - luma[y * stride + x] as i64;
+                error += (diff * diff) as u64;
+            }
+        }
+    }
+    error
+}
+
+#[test]
+fn disable_psy_measures_better() {
+    let (with, without) = (luma_error(true), luma_error(false));
+    assert!(without < with, "{} >= {}", without, with);
+}
+
+#[test]
+fn flush_counts_down() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = Setup::preset(Preset::Slow, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    let mut out = 0;
+    for n in 0..30 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, _) = encoder.encode(n as i64, image).unwrap();
+        if !data.is_empty() {
+            out += 1;
+        }
+    }
+
+    let mut flush = encoder.flush();
+    let mut remaining = flush.remaining();
+    assert!(remaining > 0);
+    assert_eq!(remaining, 30 - out);
+    while let Some(result) = flush.next() {
+        result.unwrap();
+        assert_eq!(flush.remaining(), remaining - 1);
+        remaining -= 1;
+    }
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn live_cbr_keeps_each_second_at_the_bitrate() {
+    let (width, height) = (128, 96);
+    let build = || {
+        Setup::preset(Preset::Veryfast, Tune::None, false, false)
+            .keyint(30)
+            .live_cbr(BitRate::kbps(500), 30, 1)
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+    };
+    assert_eq!(build().rate_control_mode(), RateControl::AverageBitrate);
+    assert_eq!(delay(build()), 0);
+
+    let mut encoder = build();
+    let mut seconds = [0; 5];
+    for n in 0..150 {
+        let buf = common::frame(width, height, n * (n % 7 + 1));
+        let image = common::image(&buf, width, height);
+        let (data, _) = encoder.encode(n as i64, image).unwrap();
+        seconds[n as usize / 30] += data.entirety().len();
+    }
+
+    // The first second also spends what the buffer starts out with.
+    let target = 500 * 1000 / 8;
+    for &size in &seconds[1..] {
+        assert!(size * 100 > target * 90 && size * 100 < target * 110,
+            "{} bytes in a second", size);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn run_channel_encodes_what_comes_through() {
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let build = || {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+    };
+    let owned = |buf: Vec<u8>, width: i32, height: i32| {
+        let luma = (width * height) as usize;
+        let chroma = luma / 4;
+        let plane = |stride, data: &[u8]| {
+            OwnedPlane { stride, data: data.to_vec() }
+        };
+        OwnedImage::from_planes(Colorspace::I420, width, height, vec![
+            plane(width, &buf[..luma]),
+            plane(width / 2, &buf[luma..luma + chroma]),
+            plane(width / 2, &buf[luma + chroma..]),
+        ])
+    };
+
+    let (frames, rx) = channel();
+    let (tx, results) = channel();
+    let encoder = build();
+    let thread = thread::spawn(move || encoder.run_channel(rx, tx));
+
+    for n in 0..20 {
+        let image = owned(common::frame(width, height, n), width, height);
+        frames.send((n as i64, image)).unwrap();
+    }
+    let small = owned(common::frame(32, 32, 0), 32, 32);
+    frames.send((20, small)).unwrap();
+    drop(frames);
+
+    let mut out = Vec::new();
+    let mut mismatches = 0;
+    for result in results {
+        match result {
+            Ok(data) => out.extend_from_slice(data.entirety()),
+            Err(Error::ImageMismatch { .. }) => mismatches += 1,
+            Err(error) => panic!("{}", error),
+        }
+    }
+    thread.join().unwrap();
+
+    let mut expected = Vec::new();
+    common::encode(build(), 20, |data, _| {
+        expected.extend_from_slice(data.entirety());
+    });
+    assert_eq!(mismatches, 1);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn thread_config_reports_the_split() {
+    let config = |setup: Setup| {
+        setup.build(Colorspace::I420, 1280, 720).unwrap().thread_config()
+    };
+    let slow = || Setup::preset(Preset::Slow, Tune::None, false, false);
+
+    let auto = config(slow().threads(0));
+    assert!(!auto.sliced);
+    assert!(auto.frame_threads >= 1);
+    assert!(auto.lookahead_threads >= 1);
+    assert!(auto.lookahead_threads <= auto.frame_threads);
+
+    assert_eq!(config(slow().threads(12)), ThreadConfig {
+        frame_threads: 12,
+        lookahead_threads: 2,
+        sliced: false,
+    });
+    assert_eq!(config(slow().threads(12).lookahead_threads(3)), ThreadConfig {
+        frame_threads: 12,
+        lookahead_threads: 3,
+        sliced: false,
+    });
+    assert_eq!(config(common::fast().threads(4)), ThreadConfig {
+        frame_threads: 4,
+        lookahead_threads: 4,
+        sliced: true,
+    });
+}
+
+/// The size of a fade to black, with some reference frames and some
+/// weighted prediction.
+#[cfg(feature = "alloc")]
+fn fade(refs: u32, weightp: WeightedPrediction) -> usize {
+    let (width, height) = (160, 96);
+    let setup = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .ref_frames(refs)
+        .weighted_pred(weightp);
+    let one_ref = setup.validate().contains(&Warning::SmartWeightpWithOneRef);
+    assert_eq!(one_ref, refs == 1 && weightp == WeightedPrediction::Smart);
+    let mut encoder = setup
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let mut size = 0;
+    for n in 0..60 {
+        let light = 70 - n;
+        let buf: Vec<u8> = common::frame(width, height, 0)
+            .into_iter()
+            .map(|sample| (sample as u32 * light / 70) as u8)
+            .collect();
+        let image = common::image(&buf, width, height);
+        let (data, _) = encoder.encode(n as i64, image).unwrap();
+        size += data.entirety().len();
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        size += result.unwrap().0.entirety().len();
+    }
+    size
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn smart_weightp_needs_more_refs() {
+    use WeightedPrediction::*;
+
+    // Only the settings in the SEI differ, and not in length.
+    assert_eq!(fade(1, Smart), fade(1, Simple));
+    assert!(fade(3, Smart) < fade(3, None));
+}
+
+#[test]
+fn ultra_low_latency_holds_nothing_back() {
+    let (width, height) = (640, 480);
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(4)
+        .ultra_low_latency()
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    assert_eq!(encoder.estimated_latency_frames(), 0);
+
+    for n in 0..10 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, picture) = encoder.encode(n as i64, image).unwrap();
+        assert_eq!(picture.pts(), n as i64);
+        assert_eq!(common::slices(&data), 4);
+    }
+    assert_eq!(encoder.flush().remaining(), 0);
+}
+
+#[test]
+fn mb_grid_rounds_up() {
+    let grid = |setup: Setup, width, height| {
+        let encoder = setup.build(Colorspace::I420, width, height).unwrap();
+        (encoder.mb_width(), encoder.mb_height())
+    };
+
+    assert_eq!(grid(common::fast(), 1920, 1080), (120, 68));
+    assert_eq!(grid(common::fast(), 64, 48), (4, 3));
+    assert_eq!(grid(common::fast(), 100, 50), (7, 4));
+
+    // Interlaced macroblocks come in pairs, so an odd row count goes up one.
+    let interlaced = || common::fast().fake_interlaced(true);
+    assert_eq!(grid(interlaced(), 1920, 1080), (120, 68));
+    assert_eq!(grid(interlaced(), 64, 48), (4, 4));
+
+    // Offsets sized by the grid are the ones the encoder takes.
+    let mut encoder = interlaced()
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let mbs = (encoder.mb_width() * encoder.mb_height()) as usize;
+    let offsets = vec![-2.0; mbs];
+    let buf = common::frame(common::WIDTH, common::HEIGHT, 0);
+    let image = common::image(&buf, common::WIDTH, common::HEIGHT);
+    let builder = PictureBuilder::new().quant_offsets(&offsets);
+    encoder.encode_picture(0, image, builder).unwrap();
+}
+
+/// The size of 8 frames of the gradient with faint noise over it, a little
+/// like film grain.
+fn grainy(grain: Option<GrainStrength>) -> usize {
+    let (width, height) = (128, 96);
+    let mut setup = Setup::preset(Preset::Medium, Tune::None, false, true)
+        .threads(1);
+    if let Some(strength) = grain {
+        setup = setup.preserve_grain(strength);
+    }
+    let mut encoder = setup.build(Colorspace::I420, width, height).unwrap();
+
+    let (mut x, mut size) = (2_654_435_761u32, 0);
+    for n in 0..8 {
+        let mut buf = common::frame(width, height, n);
+        for pixel in &mut buf[..(width * height) as usize] {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            *pixel = (*pixel as u32 + (x >> 24) % 9) as u8;
+        }
+        let image = common::image(&buf, width, height);
+        size += encoder.encode(n as i64, image).unwrap().0.entirety().len();
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        size += result.unwrap().0.entirety().len();
+    }
+    size
+}
+
+#[test]
+fn preserve_grain_keeps_more_in_steps() {
+    let sizes = [
+        grainy(None),
+        grainy(Some(GrainStrength::Light)),
+        grainy(Some(GrainStrength::Medium)),
+        grainy(Some(GrainStrength::Strong)),
+    ];
+    for pair in sizes.windows(2) {
+        assert!(pair[0] < pair[1], "{:?}", sizes);
+    }
+}
+
+#[test]
+fn latency_profiles_hold_back_more_in_steps() {
+    let profiles = [
+        LatencyProfile::UltraLow,
+        LatencyProfile::Low,
+        LatencyProfile::Balanced,
+        LatencyProfile::Quality,
+    ];
+
+    let mut delays = Vec::new();
+    for &profile in &profiles {
+        let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .latency_profile(profile)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let estimated = encoder.estimated_latency_frames();
+        delays.push((estimated, delay(encoder)));
+    }
+
+    assert_eq!(delays[0], (0, 0));
+    assert!(delays[3].1 >= 60, "{:?}", delays);
+    for pair in delays.windows(2) {
+        assert!(pair[0].1 < pair[1].1, "{:?}", delays);
+    }
+    for &(estimated, measured) in &delays {
+        assert_eq!(measured, estimated);
+    }
+}
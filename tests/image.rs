@@ -0,0 +1,230 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+#[cfg(feature = "alloc")]
+#[test]
+fn alignment_is_x264s() {
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, 100, 50)
+        .unwrap();
+    let align = encoder.input_alignment();
+
+    // x264's NATIVE_ALIGN, from common/osdep.h.
+    if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        assert_eq!(align, 64);
+    } else {
+        assert_eq!(align, 16);
+    }
+
+    let owned = OwnedImage::new(Colorspace::I420, 100, 50);
+    for i in 0..3 {
+        assert_eq!(owned.stride(i) as usize % align, 0);
+    }
+
+    // Odd strides still work, since x264 copies the input either way.
+    let buf = vec![128; 101 * 50 + 2 * 51 * 25];
+    let image = Image::new(Colorspace::I420, 100, 50, &[
+        Plane { stride: 101, data: &buf[..101 * 50] },
+        Plane { stride: 51, data: &buf[101 * 50..101 * 50 + 51 * 25] },
+        Plane { stride: 51, data: &buf[101 * 50 + 51 * 25..] },
+    ]);
+    assert!(encoder.encode(0, image).is_ok());
+    assert!(encoder.encode(1, owned.as_image()).is_ok());
+}
+
+#[test]
+fn supported_encodings_build() {
+    assert!(Encoding::from(Colorspace::I420).is_supported());
+
+    use Colorspace::*;
+
+    for &csp in &[
+        I400, I420, YV12, NV12, NV21, I422, YV16, NV16, V210,
+        I444, YV24, BGR, BGRA, RGB,
+    ] {
+        for &high in &[false, true] {
+            let mut format = Encoding::from(csp);
+            let mut setup = common::fast();
+            if high {
+                format = format.add(Modifier::HighDepth);
+                setup = setup.bit_depth(10);
+            }
+
+            let built = setup
+                .build(format, common::WIDTH, common::HEIGHT)
+                .is_ok();
+            assert_eq!(built, format.is_supported(), "{}", format);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn encode_8bit_widens_the_input() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let high = Encoding::from(Colorspace::I420).add(Modifier::HighDepth);
+    let build = || {
+        common::fast()
+            .bit_depth(10)
+            .build(high, width, height)
+            .unwrap()
+    };
+
+    let buf = common::frame(width, height, 0);
+    let mut encoder = build();
+    let narrow = encoder
+        .encode_8bit(0, common::image(&buf, width, height))
+        .unwrap()
+        .0
+        .entirety()
+        .to_vec();
+
+    // The same samples, shifted up by hand.
+    let wide: Vec<u8> = buf
+        .iter()
+        .flat_map(|&sample| ((sample as u16) << 2).to_ne_bytes())
+        .collect();
+    let luma = 2 * (width * height) as usize;
+    let chroma = luma / 4;
+    let image = Image::new(high, width, height, &[
+        Plane { stride: 2 * width, data: &wide[..luma] },
+        Plane { stride: width, data: &wide[luma..luma + chroma] },
+        Plane { stride: width, data: &wide[luma + chroma..] },
+    ]);
+    let mut encoder = build();
+    let (data, picture) = encoder.encode(0, image).unwrap();
+
+    assert!(picture.keyframe());
+    assert_eq!(narrow, data.entirety());
+
+    let sps = encoder.sps_info().unwrap();
+    assert_eq!((sps.profile_idc, sps.bit_depth_luma), (110, 10));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn gray_frames_encode_small() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let luma = &common::frame(width, height, 0)[..(width * height) as usize];
+    let gray = |csp| {
+        let mut encoder = common::fast().build(csp, width, height).unwrap();
+        let format = encoder.sps_info().unwrap().chroma_format_idc;
+        let (data, _) = encoder
+            .encode_gray(0, Plane { stride: width, data: luma })
+            .unwrap();
+        let slice: usize = data
+            .units()
+            .filter(|unit| unit.unit_type() == NalUnitType::SliceIdr)
+            .map(|unit| unit.as_ref().len())
+            .sum();
+        (data.entirety().to_vec(), slice, format)
+    };
+
+    // The headers and their SEI differ, so compare the slices. Neutral
+    // chroma costs next to nothing either way.
+    let (_, mono, format) = gray(Colorspace::I400);
+    assert_eq!(format, 0);
+    let (i420, slice, format) = gray(Colorspace::I420);
+    assert_eq!(format, 1);
+    assert!(mono <= slice);
+    assert!(mono < luma.len() / 2);
+    assert_eq!(gray(Colorspace::NV12).2, 1);
+    assert_eq!(gray(Colorspace::I444).2, 3);
+
+    // The chroma made up for I420 is neutral.
+    let mut buf = luma.to_vec();
+    buf.resize(buf.len() * 3 / 2, 128);
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    let (data, _) = encoder
+        .encode(0, common::image(&buf, width, height))
+        .unwrap();
+    assert_eq!(data.entirety(), &i420[..]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn owned_images_can_be_refilled() {
+    let (width, height) = (72, 48);
+    let build = || {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+    };
+
+    let mut image = OwnedImage::new(Colorspace::I420, width, height);
+    let rows = [height as usize, height as usize / 2, height as usize / 2];
+    let widths = [width as usize, width as usize / 2, width as usize / 2];
+
+    let mut encoder = build();
+    let mut out = Vec::new();
+    for n in 0..10 {
+        let buf = common::frame(width, height, n);
+        let mut source = &buf[..];
+        for i in 0..3 {
+            let stride = image.stride(i) as usize;
+            let plane = image.plane_mut(i);
+            for y in 0..rows[i] {
+                let row = &mut plane[y * stride..y * stride + widths[i]];
+                row.copy_from_slice(&source[..widths[i]]);
+                source = &source[widths[i]..];
+            }
+        }
+        let (data, _) = encoder.encode(n as i64, image.as_image()).unwrap();
+        out.extend_from_slice(data.entirety());
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        out.extend_from_slice(result.unwrap().0.entirety());
+    }
+
+    let mut expected = Vec::new();
+    common::encode(build(), 10, |data, _| {
+        expected.extend_from_slice(data.entirety());
+    });
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn raw_images_round_trip() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let setup = || common::fast().threads(1);
+    let mut plain = setup().build(Colorspace::I420, width, height).unwrap();
+    let mut bridged = setup().build(Colorspace::I420, width, height).unwrap();
+
+    for n in 0..5 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let raw = *image.as_raw_image();
+
+        let luma = (width * height) as usize;
+        let planes = [
+            &buf[..luma],
+            &buf[luma..luma * 5 / 4],
+            &buf[luma * 5 / 4..],
+        ];
+        assert_eq!(raw.i_plane, 3);
+        for (i, plane) in planes.iter().enumerate() {
+            let data = unsafe {
+                std::slice::from_raw_parts(raw.plane[i], plane.len())
+            };
+            assert_eq!(data, *plane);
+        }
+
+        let copy = unsafe { Image::from_raw_image(raw, width, height) };
+        assert_eq!(copy.encoding(), image.encoding());
+        assert_eq!(copy.as_raw_image().plane, raw.plane);
+        assert_eq!(copy.as_raw_image().i_stride, raw.i_stride);
+
+        let (data, _) = plain.encode(n as i64, image).unwrap();
+        let expected = data.entirety().to_vec();
+        assert!(!expected.is_empty());
+        let (data, _) = bridged.encode(n as i64, copy).unwrap();
+        assert_eq!(data.entirety(), &expected[..]);
+    }
+}
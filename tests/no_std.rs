@@ -0,0 +1,48 @@
+//! Encodes from a crate without `std`, as embedded users would. Run with
+//! `cargo test --no-default-features --features alloc --test no_std` to
+//! check that the crate still builds and works without it.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+extern crate x264;
+
+use x264::*;
+
+#[test]
+fn encodes_without_std() {
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    let mut buf = [128; WIDTH * HEIGHT * 3 / 2];
+    for (i, pixel) in buf[..WIDTH * HEIGHT].iter_mut().enumerate() {
+        *pixel = (16 + i % WIDTH * 3) as u8;
+    }
+    let (luma, chroma) = buf.split_at(WIDTH * HEIGHT);
+    let (u, v) = chroma.split_at(WIDTH * HEIGHT / 4);
+    let image = Image::new(
+        Colorspace::I420,
+        WIDTH as i32,
+        HEIGHT as i32,
+        &[
+            Plane { stride: WIDTH as i32, data: luma },
+            Plane { stride: WIDTH as i32 / 2, data: u },
+            Plane { stride: WIDTH as i32 / 2, data: v },
+        ],
+    );
+
+    let mut encoder = Setup::preset(Preset::Ultrafast, Tune::None, false, true)
+        .build(Colorspace::I420, WIDTH as i32, HEIGHT as i32)
+        .unwrap();
+    let (data, picture) = encoder.encode(0, image).unwrap();
+    assert!(picture.keyframe());
+    assert_eq!(&data.entirety()[..4], &[0, 0, 0, 1]);
+
+    #[cfg(feature = "alloc")]
+    {
+        let owned = DataBuf::from(&data);
+        let units: alloc::vec::Vec<_> = owned.units().collect();
+        assert_eq!(units.len(), data.len());
+    }
+}
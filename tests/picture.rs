@@ -0,0 +1,352 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+#[test]
+fn crc32_is_reproducible() {
+    let run = |crc32| {
+        let encoder = Setup::preset(Preset::Fast, Tune::None, false, false)
+            .benchmark_mode(2)
+            .recon_crc32(crc32)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let mut sums = Vec::new();
+        common::encode(encoder, 12, |_, picture| sums.push(picture.crc32()));
+        sums
+    };
+
+    let first = run(true);
+    assert_eq!(first.len(), 12);
+    assert!(first.iter().all(Option::is_some));
+    assert_eq!(first, run(true));
+    assert!(run(false).iter().all(Option::is_none));
+}
+
+#[test]
+fn size_is_what_comes_out() {
+    for &annexb in &[true, false] {
+        let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+            .annexb(annexb)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+
+        let mut frames = 0;
+        common::encode(encoder, 10, |data, picture| {
+            assert!(picture.size_bytes() > 0);
+            assert_eq!(picture.size_bytes(), data.entirety().len());
+            frames += 1;
+        });
+        assert_eq!(frames, 10);
+    }
+}
+
+#[test]
+fn only_plain_b_frames_are_disposable() {
+    let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .bframes(3)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut disposable = 0;
+    common::encode(encoder, 30, |data, picture| {
+        let b = picture.frame_type() == FrameType::B;
+        assert_eq!(picture.is_reference(), !b);
+        if b {
+            disposable += 1;
+        }
+
+        for unit in data.units() {
+            let kind = unit.unit_type();
+            if kind == NalUnitType::Slice || kind == NalUnitType::SliceIdr {
+                assert_eq!(unit.priority() == Priority::Disposable, b);
+            }
+        }
+    });
+    assert!(disposable > 0);
+}
+
+#[test]
+fn normal_pyramid_has_both_kinds_of_b_frames() {
+    let types = |pyramid| {
+        let encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .bframes(3)
+            .b_pyramid(pyramid)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let types: Vec<_> = common::pictures(encoder, 30)
+            .into_iter()
+            .map(|(_, kind, _)| kind)
+            .collect();
+        (types.contains(&FrameType::BRef), types.contains(&FrameType::B))
+    };
+
+    assert_eq!(types(BPyramid::Normal), (true, true));
+    assert_eq!(types(BPyramid::None), (false, true));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn builder_options_apply_together() {
+    const USER_DATA_UNREGISTERED: i32 = 5;
+
+    let run = |offset: f32| {
+        // Ultrafast has no adaptive quantization, which the offsets need.
+        let setup = Setup::preset(Preset::Veryfast, Tune::None, false, true);
+        let mut encoder = setup
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let (width, height) = (common::WIDTH, common::HEIGHT);
+        let mbs = (encoder.mb_width() * encoder.mb_height()) as usize;
+        let offsets = vec![offset; mbs];
+        let mut sei = vec![0x42; 16];
+        sei.extend_from_slice(b"hello");
+
+        let mut out = None;
+        for n in 0..8 {
+            let buf = common::frame(width, height, n);
+            let image = common::image(&buf, width, height);
+            if n != 5 {
+                let (_, picture) = encoder.encode(n as i64, image).unwrap();
+                assert_eq!(picture.opaque(), 0);
+                continue;
+            }
+
+            let builder = PictureBuilder::new()
+                .frame_type(FrameTypeHint::Idr)
+                .quant_offsets(&offsets)
+                .sei(USER_DATA_UNREGISTERED, &sei)
+                .opaque(1234);
+            let (data, picture) = encoder
+                .encode_picture(n as i64, image, builder)
+                .unwrap();
+            assert_eq!(picture.frame_type(), FrameType::Idr);
+            assert_eq!(picture.opaque(), 1234);
+            let messages = common::sei(&data, USER_DATA_UNREGISTERED as u32);
+            assert!(messages.contains(&sei));
+            out = Some(picture.size_bytes());
+        }
+        out.unwrap()
+    };
+
+    assert!(run(-6.0) > run(0.0));
+}
+
+#[test]
+fn temporal_layers_cycle() {
+    let layers = |layers| {
+        let encoder = common::fast()
+            .threads(1)
+            .temporal_layers(layers)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let mut ids = Vec::new();
+        common::encode(encoder, 25, |_, picture| {
+            ids.push((picture.pts(), picture.temporal_id()));
+        });
+        ids.sort();
+        ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>()
+    };
+
+    let three = layers(3);
+    assert_eq!(three.len(), 25);
+    assert_eq!(three[0], 0);
+    for chunk in three[1..].chunks(4) {
+        assert_eq!(chunk, [2, 1, 2, 0]);
+    }
+
+    let two = layers(2);
+    assert_eq!(two[0], 0);
+    for chunk in two[1..].chunks(2) {
+        assert_eq!(chunk, [1, 0]);
+    }
+
+    assert!(layers(1).into_iter().all(|id| id == 0));
+}
+
+#[test]
+fn pts_come_back_reordered() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let mut out = Vec::new();
+    for n in 0..30 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, picture) = encoder.encode(n as i64 * 10, image).unwrap();
+        if !data.is_empty() {
+            out.push(picture.pts());
+        }
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        out.push(result.unwrap().1.pts());
+    }
+
+    let mut sorted = out.clone();
+    sorted.sort();
+    assert_ne!(out, sorted);
+    assert_eq!(sorted, (0..30).map(|n| n * 10).collect::<Vec<i64>>());
+}
+
+#[test]
+fn flushed_frames_follow_in_dts_order() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = Setup::preset(Preset::Medium, Tune::None, false, false)
+        .threads(1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let mut out = Vec::new();
+    for n in 0..30 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, picture) = encoder.encode(n as i64, image).unwrap();
+        if !data.is_empty() {
+            out.push((picture.pts(), picture.dts()));
+        }
+    }
+    let encoded = out.len();
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        let picture = result.unwrap().1;
+        out.push((picture.pts(), picture.dts()));
+    }
+    assert!(encoded < out.len());
+
+    for pair in out.windows(2) {
+        assert!(pair[0].1 < pair[1].1, "{:?}", out);
+    }
+    for &(pts, dts) in &out {
+        assert!(dts <= pts, "{:?}", out);
+    }
+
+    let mut pts: Vec<i64> = out.iter().map(|&(pts, _)| pts).collect();
+    pts.sort();
+    assert_eq!(pts, (0..30).collect::<Vec<i64>>());
+}
+
+/// The size of some frames, and the squared error of their reconstructed
+/// luma within the 96x120 rectangle at (100, 60), with the builders that
+/// `builder` gives.
+#[cfg(feature = "alloc")]
+fn face<'a, F>(mut builder: F) -> (Vec<u8>, u64)
+where
+    F: FnMut() -> PictureBuilder<'a>,
+{
+    let (width, height) = (320, 240);
+    let mut encoder = Setup::preset(Preset::Veryfast, Tune::None, false, true)
+        .threads(1)
+        .full_recon(true)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let (mut out, mut error) = (Vec::new(), 0);
+    for n in 0..5 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let (data, _) = encoder
+            .encode_picture(n as i64, image, builder())
+            .unwrap();
+        out.extend_from_slice(data.entirety());
+
+        let recon = encoder.reconstructed().unwrap();
+        let (luma, stride) = (recon.plane(0), recon.stride(0) as usize);
+        for y in 60..180 {
+            for x in 100..196 {
+                let source = buf[y * width as usize + x] as i64;
+                let diff = source - luma[y * stride + x] as i64;
+                error += (diff * diff) as u64;
+            }
+        }
+    }
+    (out, error)
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn roi_spends_more_on_the_rectangle() {
+    let roi = || {
+        PictureBuilder::new()
+            .roi(Rect { x: 100, y: 60, width: 96, height: 120 }, -8.0)
+            .roi(Rect { x: 300, y: -20, width: 100, height: 40 }, -1.0)
+            .roi(Rect { x: 180, y: 150, width: 32, height: 32 }, -2.0)
+    };
+
+    // The same, by hand, on the 20x15 macroblock grid.
+    let mut offsets = vec![0.0; 20 * 15];
+    for y in 0..15 {
+        for x in 0..20 {
+            let offset = &mut offsets[y * 20 + x];
+            if (6..13).contains(&x) && (3..12).contains(&y) {
+                *offset -= 8.0;
+            }
+            if x >= 18 && y < 2 {
+                *offset -= 1.0;
+            }
+            if (11..14).contains(&x) && (9..12).contains(&y) {
+                *offset -= 2.0;
+            }
+        }
+    }
+    let by_hand = || PictureBuilder::new().quant_offsets(&offsets);
+
+    let (plain, plain_error) = face(PictureBuilder::new);
+    let (rois, rois_error) = face(roi);
+    assert!(rois.len() > plain.len());
+    assert!(rois_error < plain_error);
+    assert_eq!(rois, face(by_hand).0);
+}
+
+/// The size of each of 6 intra frames, with AQ at `strength` on the 4th.
+#[cfg(feature = "alloc")]
+fn aq_sizes(strength: f32) -> Vec<usize> {
+    let (width, height) = (128, 96);
+    let mut encoder = Setup::preset(Preset::Veryfast, Tune::None, false, true)
+        .threads(1)
+        .keyint(1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    let mut sizes = Vec::new();
+    for n in 0..6 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        let builder = if n == 3 {
+            PictureBuilder::new().aq_strength(strength)
+        } else {
+            PictureBuilder::new()
+        };
+        let (data, _) = encoder
+            .encode_picture(n as i64, image, builder)
+            .unwrap();
+        sizes.push(data.entirety().len());
+    }
+    sizes
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn aq_strength_changes_one_frame() {
+    let plain = aq_sizes(0.0);
+    let strengths = [-0.8, 0.5, 1.5];
+    let sizes: Vec<Vec<usize>> =
+        strengths.iter().map(|&strength| aq_sizes(strength)).collect();
+
+    for frame in &sizes {
+        assert_eq!(frame[..3], plain[..3]);
+        assert_eq!(frame[4..], plain[4..]);
+    }
+
+    // The texture is busy everywhere, so stronger AQ raises the whole
+    // frame's QP.
+    assert!(sizes[0][3] > plain[3]);
+    assert!(plain[3] > sizes[1][3]);
+    assert!(sizes[1][3] > sizes[2][3]);
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "memmap2")]
+
+extern crate x264;
+
+mod common;
+
+use std::{env, fs, process};
+use x264::*;
+
+#[test]
+fn mapped_frames_encode_like_the_originals() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let name = format!("x264-reader-{}.yuv", process::id());
+    let path = env::temp_dir().join(name);
+
+    // Five frames, and the start of a sixth, which is left out.
+    let mut file = Vec::new();
+    for n in 0..5 {
+        file.extend(common::frame(width, height, n));
+    }
+    file.extend_from_slice(&[0; 100]);
+    fs::write(&path, &file).unwrap();
+
+    let reader = RawYuvReader::open(&path, Colorspace::I420, width, height)
+        .unwrap();
+    assert_eq!(reader.len(), 5);
+    assert!(reader.frame(5).is_none());
+
+    let build = || {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+    };
+    let mut encoder = build();
+    let mut out = Vec::new();
+    for (n, image) in reader.frames().enumerate() {
+        let (data, _) = encoder.encode(n as i64, image).unwrap();
+        out.extend_from_slice(data.entirety());
+    }
+    let mut flush = encoder.flush();
+    while let Some(result) = flush.next() {
+        out.extend_from_slice(result.unwrap().0.entirety());
+    }
+    drop(reader);
+    fs::remove_file(&path).unwrap();
+
+    let mut expected = Vec::new();
+    common::encode(build(), 5, |data, _| {
+        expected.extend_from_slice(data.entirety());
+    });
+    assert_eq!(out, expected);
+}
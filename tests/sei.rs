@@ -0,0 +1,128 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+const BUFFERING_PERIOD: u32 = 0;
+const PIC_TIMING: u32 = 1;
+const RECOVERY_POINT: u32 = 6;
+const FRAME_PACKING: u32 = 45;
+
+#[test]
+fn frame_packing_matches_x264() {
+    use FramePacking::*;
+
+    let all = [
+        Checkerboard, ColumnInterleaved, RowInterleaved, SideBySide,
+        TopBottom, FrameAlternation, Mono2d, TileFormat,
+    ];
+
+    for &packing in &all {
+        let encoder = common::fast()
+            .frame_packing(packing)
+            .build(Colorspace::I420, 96, 96)
+            .unwrap();
+
+        let mut n = 0;
+        common::encode(encoder, 2, |data, _| {
+            let sei = common::sei(data, FRAME_PACKING);
+            if n == 0 || packing == FrameAlternation {
+                let (payload, len) = packing.sei_payload(n % 2 == 0);
+                assert_eq!(sei, [&payload[..len]], "{:?}", packing);
+            }
+            n += 1;
+        });
+    }
+}
+
+#[test]
+fn hrd_buffering_period_at_each_idr() {
+    let rate = BitRate::kbps(500);
+    let encoder = common::fast()
+        .bitrate(rate)
+        .vbv(rate, 500)
+        .nal_hrd(NalHrd::Cbr)
+        .keyint(5)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let mut frames = 0;
+    common::encode(encoder, 12, |data, picture| {
+        let idr = picture.frame_type() == FrameType::Idr;
+        assert_eq!(idr, frames % 5 == 0);
+        assert_eq!(common::sei(data, BUFFERING_PERIOD).len(), idr as usize);
+        assert_eq!(common::sei(data, PIC_TIMING).len(), 1);
+        frames += 1;
+    });
+    assert_eq!(frames, 12);
+}
+
+/// Reads an Exp-Golomb number from the start of a payload.
+fn ue(payload: &[u8]) -> u32 {
+    let bit = |i: usize| (payload[i / 8] >> (7 - i % 8)) & 1;
+    let mut zeros = 0;
+    while bit(zeros) == 0 {
+        zeros += 1;
+    }
+    let mut value = 1;
+    for i in 0..zeros {
+        value = value << 1 | bit(zeros + 1 + i) as u32;
+    }
+    value - 1
+}
+
+#[test]
+fn recovery_point_after_each_sweep() {
+    // 20 macroblocks wide, so a sweep takes the whole period.
+    let encoder = common::fast()
+        .intra_refresh_period(10)
+        .build(Colorspace::I420, 320, 48)
+        .unwrap();
+
+    let mut points = Vec::new();
+    common::encode(encoder, 30, |data, picture| {
+        let sei = common::sei(data, RECOVERY_POINT);
+        if picture.is_recovery_point() {
+            points.push(picture.pts());
+            // x264 counts the frames until the sweep has made it across.
+            assert_eq!(sei.len(), 1);
+            assert_eq!(ue(&sei[0]), 9);
+        } else {
+            assert!(sei.is_empty());
+        }
+    });
+    assert_eq!(points, [10, 20]);
+}
+
+/// The timestamps of the frames with frame packing SEI, and how many each
+/// has, with a keyframe every 20 frames and the SEI repeated every
+/// `repeat` frames.
+#[cfg(feature = "alloc")]
+fn packing_repeats(repeat: u32) -> Vec<(i64, usize)> {
+    let packing = FramePacking::SideBySide;
+    let encoder = common::fast()
+        .keyint(20)
+        .frame_packing(packing)
+        .frame_packing_repeat(repeat)
+        .build(Colorspace::I420, 96, 64)
+        .unwrap();
+    let (payload, len) = packing.sei_payload(true);
+
+    let mut out = Vec::new();
+    common::encode(encoder, 35, |data, picture| {
+        let sei = common::sei(data, FRAME_PACKING);
+        if !sei.is_empty() {
+            assert!(sei.iter().all(|sei| sei[..] == payload[..len]));
+            out.push((picture.pts(), sei.len()));
+        }
+    });
+    out
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn frame_packing_repeats() {
+    assert_eq!(packing_repeats(0), [(0, 1), (20, 1)]);
+    assert_eq!(packing_repeats(10), [(0, 1), (10, 1), (20, 2), (30, 1)]);
+}
@@ -0,0 +1,478 @@
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+#[test]
+fn overscan_reaches_the_sps() {
+    let cases = [
+        (Overscan::Unspecified, None),
+        (Overscan::Show, Some(false)),
+        (Overscan::Crop, Some(true)),
+    ];
+
+    for &(overscan, flag) in &cases {
+        let mut encoder = common::fast()
+            .overscan(overscan)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let raw = encoder.effective_params().raw().vui.i_overscan;
+        assert_eq!(raw, overscan as i32);
+        assert_eq!(encoder.sps_info().unwrap().overscan, flag);
+    }
+}
+
+#[test]
+fn fake_interlaced_only_flags_the_sps() {
+    let mut encoder = common::fast()
+        .fake_interlaced(true)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let params = encoder.effective_params();
+    assert_eq!(params.raw().b_fake_interlaced, 1);
+    assert_eq!(params.raw().b_interlaced, 0);
+
+    let sps = encoder.sps_info().unwrap();
+    assert!(!sps.frame_mbs_only);
+    assert_eq!((sps.width, sps.height), (64, 48));
+
+    let mut frames = 0;
+    common::encode(encoder, 4, |_, _| frames += 1);
+    assert_eq!(frames, 4);
+}
+
+#[test]
+fn total_frames_reaches_params() {
+    let encoder = common::fast()
+        .total_frames(300)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    assert_eq!(encoder.effective_params().raw().i_frame_total, 300);
+}
+
+#[test]
+fn presets_read_back() {
+    let build = |preset| {
+        Setup::preset(preset, Tune::None, false, false)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap()
+            .effective_params()
+    };
+
+    let ultrafast = build(Preset::Ultrafast);
+    assert_eq!(ultrafast.rate_control(), RateControl::ConstantRateFactor);
+    assert_eq!(ultrafast.crf(), 23.0);
+    assert_eq!(ultrafast.bframes(), 0);
+    assert_eq!(ultrafast.ref_frames(), 1);
+    assert!(!ultrafast.cabac());
+    assert_eq!(ultrafast.keyint(), (25, 250));
+    assert_eq!(ultrafast.fps(), (25, 1));
+
+    let medium = build(Preset::Medium);
+    assert_eq!(medium.rate_control(), RateControl::ConstantRateFactor);
+    assert_eq!(medium.bframes(), 3);
+    assert_eq!(medium.ref_frames(), 3);
+    assert!(medium.cabac());
+    assert_eq!(medium.level(), Some(Level::L1));
+}
+
+#[test]
+fn color_matrix_has_to_match_the_input() {
+    let build = |matrix, csp| {
+        common::fast()
+            .color_matrix(matrix)
+            .build(csp, common::WIDTH, common::HEIGHT)
+    };
+
+    assert_eq!(build(ColorMatrix::Bt709, Colorspace::RGB).err(),
+        Some(Error::Failed));
+    assert_eq!(build(ColorMatrix::Gbr, Colorspace::I420).err(),
+        Some(Error::Failed));
+    assert!(build(ColorMatrix::Gbr, Colorspace::RGB).is_ok());
+    assert!(build(ColorMatrix::Bt709, Colorspace::I420).is_ok());
+
+    let sps = |matrix: Option<ColorMatrix>, csp| {
+        let mut setup = common::fast();
+        if let Some(matrix) = matrix {
+            setup = setup.color_matrix(matrix);
+        }
+        let mut encoder = setup
+            .build(csp, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        encoder.sps_info().unwrap().matrix
+    };
+
+    let gbr = Some(ColorMatrix::Gbr as u8);
+    assert_eq!(sps(None, Colorspace::BGRA), gbr);
+    assert_eq!(sps(Some(ColorMatrix::Gbr), Colorspace::RGB), gbr);
+    assert_eq!(sps(None, Colorspace::I420), None);
+    assert_eq!(sps(Some(ColorMatrix::Bt709), Colorspace::I420),
+        Some(ColorMatrix::Bt709 as u8));
+}
+
+#[test]
+fn bluray_compat_caps_the_settings() {
+    let setup = || {
+        Setup::preset(Preset::Placebo, Tune::None, false, false)
+            .ref_frames(16)
+            .bframes(8)
+            .b_pyramid(BPyramid::Normal)
+            .bluray_compat(true)
+    };
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let rate = BitRate::kbps(40_000);
+
+    assert_eq!(setup().build(Colorspace::I420, width, height).err(),
+        Some(Error::Failed));
+    let sliced = setup().vbv(rate, 30_000).slice_max_size(1000);
+    assert_eq!(sliced.build(Colorspace::I420, width, height).err(),
+        Some(Error::Failed));
+
+    let encoder = setup()
+        .vbv(rate, 30_000)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    let params = encoder.effective_params();
+    assert_eq!(params.bframes(), 3);
+    assert_eq!(params.ref_frames(), 6);
+    assert_eq!(params.raw().i_bframe_pyramid, BPyramid::Strict as i32);
+    assert_eq!(params.vbv(), (40_000, 30_000));
+    assert!(encoder.dpb_size() <= 6);
+}
+
+#[test]
+fn stitchable_segments_share_headers() {
+    // The headers of a segment at a constant QP, without the SEI, which
+    // carries the settings.
+    let headers = |qp, stitchable| {
+        let cqp = RateControl::ConstantQp as i32;
+        let setup = common::fast().stitchable(stitchable);
+        let mut encoder = unsafe {
+            setup.with_raw_params(|raw| {
+                raw.rc.i_rc_method = cqp;
+                raw.rc.i_qp_constant = qp;
+            })
+        }
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+        let headers = encoder.headers().unwrap();
+        headers
+            .units()
+            .filter(|unit| unit.unit_type() != NalUnitType::Sei)
+            .flat_map(|unit| unit.as_ref().to_vec())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(headers(20, true), headers(30, true));
+    assert_ne!(headers(20, false), headers(30, false));
+}
+
+#[test]
+fn sps_info_matches_the_encoder() {
+    for &(width, height) in &[(64, 48), (1920, 1080), (100, 38)] {
+        let mut encoder = common::fast()
+            .fps(30000, 1001)
+            .build(Colorspace::I420, width, height)
+            .unwrap();
+        let sps = encoder.sps_info().unwrap();
+
+        assert_eq!(sps.width, encoder.width() as u32);
+        assert_eq!(sps.height, encoder.height() as u32);
+        assert_eq!(sps.chroma_format_idc, 1);
+        assert_eq!(sps.bit_depth_luma, 8);
+        assert_eq!(sps.timing, Some((1001, 60000)));
+    }
+
+    let high = Encoding::from(Colorspace::I444).add(Modifier::HighDepth);
+    let mut encoder = common::fast()
+        .bit_depth(10)
+        .build(high, common::WIDTH, common::HEIGHT)
+        .unwrap();
+    let sps = encoder.sps_info().unwrap();
+    assert_eq!(sps.profile_idc, 244);
+    assert_eq!(sps.chroma_format_idc, 3);
+    assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (10, 10));
+}
+
+#[test]
+fn deblock_reaches_params() {
+    let build = |setup: Setup| {
+        setup
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap()
+    };
+
+    let encoder = build(common::fast().deblock(true).deblock_strength(-3, 2));
+    let params = encoder.effective_params();
+    assert_eq!(params.raw().b_deblocking_filter, 1);
+    assert_eq!(params.raw().i_deblocking_filter_alphac0, -3);
+    assert_eq!(params.raw().i_deblocking_filter_beta, 2);
+
+    let encoder = build(common::fast().deblock(false));
+    assert_eq!(encoder.effective_params().raw().b_deblocking_filter, 0);
+
+    let on = common::size(build(common::fast().deblock(true)), 10);
+    let off = common::size(build(common::fast().deblock(false)), 10);
+    assert_ne!(on, off);
+}
+
+#[test]
+fn sps_id_reaches_the_sps() {
+    let sps_id = |id| {
+        common::fast()
+            .sps_id(id)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap()
+            .sps_info()
+            .unwrap()
+            .sps_id
+    };
+
+    assert_eq!(sps_id(0), 0);
+    assert_eq!(sps_id(3), 3);
+    assert_eq!(sps_id(17), 17);
+}
+
+#[test]
+fn mv_range_reaches_params() {
+    let (width, height) = (3840, 2160);
+    let mut encoder = common::fast()
+        .mv_range(1024)
+        .mv_range_thread(-1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    assert_eq!(encoder.effective_params().raw().analyse.i_mv_range, 1024);
+
+    let buf = common::frame(width, height, 0);
+    let image = common::image(&buf, width, height);
+    assert!(encoder.encode(0, image).is_ok());
+
+    // -1 leaves it to the level, which is 5.1 at 30 frames a second.
+    let encoder = common::fast()
+        .fps(30, 1)
+        .mv_range(-1)
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+    assert_eq!(encoder.effective_params().raw().analyse.i_mv_range, 512);
+}
+
+#[test]
+fn effective_params_are_clamped() {
+    let mut encoder = common::fast()
+        .ref_frames(40)
+        .bframes(50)
+        .fps(30, 1)
+        .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+        .unwrap();
+
+    let params = encoder.effective_params();
+    assert_eq!(params.ref_frames(), 16);
+    assert_eq!(params.bframes(), 16);
+    assert_eq!(params.fps(), (30, 1));
+    assert_eq!(params.fps(), encoder.fps());
+
+    // Reopening reads them back again.
+    encoder.reopen().unwrap();
+    assert_eq!(encoder.effective_params().ref_frames(), 16);
+}
+
+#[test]
+fn psy_toggles_both_strengths() {
+    let analyse = |setup: Setup| {
+        let encoder = setup
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        let analyse = encoder.effective_params().raw().analyse;
+        (analyse.b_psy, analyse.f_psy_rd, analyse.f_psy_trellis)
+    };
+    let grain = || Setup::preset(Preset::Medium, Tune::Grain, false, false);
+
+    assert_eq!(analyse(grain()), (1, 1.0, 0.25));
+    assert_eq!(analyse(grain().psy(false)), (0, 0.0, 0.0));
+    assert_eq!(analyse(grain().psy(false).psy(true)), (1, 1.0, 0.25));
+    assert_eq!(analyse(grain().disable_psy()), (0, 0.0, 0.0));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn cpu_flags_reach_params() {
+    // Every x86-64 CPU has SSE2, unless x264 was built without assembly.
+    let detected = CpuFlags::detect();
+    assert!(detected.is_empty() || detected.has(CpuFlags::SSE2));
+
+    let cpu = |flags| {
+        let encoder = common::fast()
+            .cpu_flags(flags)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        CpuFlags::from_raw(encoder.effective_params().raw().cpu)
+    };
+    let fewer = detected.remove(CpuFlags::AVX2 | CpuFlags::AVX512);
+    assert!(!cpu(fewer).has(CpuFlags::AVX2));
+    assert!(!cpu(fewer).has(CpuFlags::AVX512));
+    assert!(cpu(CpuFlags::empty()).is_empty());
+
+    // x264's assembly is bit-exact with its C.
+    let size = |flags| {
+        let encoder = common::fast()
+            .cpu_flags(flags)
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        common::size(encoder, 5)
+    };
+    assert_eq!(size(CpuFlags::empty()), size(detected));
+}
+
+#[test]
+fn cabac_reads_back_after_the_profile() {
+    let build = |setup: Setup| {
+        setup
+            .build(Colorspace::I420, common::WIDTH, common::HEIGHT)
+            .unwrap()
+    };
+    let medium = || Setup::preset(Preset::Medium, Tune::None, false, true);
+
+    let mut high = build(medium().high());
+    assert!(high.effective_params().cabac());
+    assert_eq!(high.sps_info().unwrap().profile_idc, 100);
+
+    let mut baseline = build(medium().baseline());
+    assert!(!baseline.effective_params().cabac());
+    assert_eq!(baseline.sps_info().unwrap().profile_idc, 66);
+
+    assert!(!build(medium().cabac(false)).effective_params().cabac());
+
+    let init = |idc| {
+        build(medium().cabac_init_idc(idc)).effective_params().cabac_init_idc()
+    };
+    assert_eq!(init(0), 0);
+    assert_eq!(init(2), 2);
+    assert_eq!(init(9), 2);
+}
+
+#[test]
+fn impossible_sizes_fail_to_build() {
+    for &(width, height) in &[(0, 0), (0, 48), (64, 0), (-16, 48)] {
+        let result = common::fast().build(Colorspace::I420, width, height);
+        assert_eq!(result.err(), Some(Error::Failed), "{}x{}", width, height);
+    }
+}
+
+#[test]
+fn rgb_is_full_range_by_default() {
+    let full_range = |setup: Setup, csp| {
+        setup
+            .build(csp, common::WIDTH, common::HEIGHT)
+            .unwrap()
+            .sps_info()
+            .unwrap()
+            .full_range
+    };
+    let fast = common::fast;
+
+    assert!(full_range(fast(), Colorspace::RGB));
+    assert!(full_range(fast(), Colorspace::BGRA));
+    assert!(!full_range(fast().full_range(false), Colorspace::RGB));
+    assert!(!full_range(fast(), Colorspace::I420));
+    assert!(full_range(fast().full_range(true), Colorspace::I420));
+}
+
+#[test]
+fn auto_subme_scales_with_the_size() {
+    let subme = |preset, width, height| {
+        Setup::preset(preset, Tune::None, false, true)
+            .auto_subme()
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+            .effective_params()
+            .raw()
+            .analyse
+            .i_subpel_refine
+    };
+
+    // Medium is 7, Slower 9, Faster 4 and Placebo 11.
+    assert_eq!(subme(Preset::Medium, 3840, 2160), 6);
+    assert_eq!(subme(Preset::Medium, 1280, 720), 7);
+    assert_eq!(subme(Preset::Medium, 640, 480), 8);
+    assert_eq!(subme(Preset::Slower, 3840, 2160), 7);
+    assert_eq!(subme(Preset::Faster, 3840, 2160), 4);
+    assert_eq!(subme(Preset::Placebo, 640, 480), 11);
+}
+
+/// The chroma QP offset, the size and the squared error of the
+/// reconstructed chroma, for some 4:4:4 frames with sharp chroma edges.
+fn sharp_chroma(auto: bool) -> (i32, usize, u64) {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut setup = Setup::preset(Preset::Medium, Tune::None, false, true)
+        .threads(1)
+        .full_recon(true);
+    if auto {
+        setup = setup.auto_chroma_qp();
+    }
+    let mut encoder = setup.build(Colorspace::I444, width, height).unwrap();
+    let offset = encoder.effective_params().raw().analyse.i_chroma_qp_offset;
+
+    let (w, h) = (width as usize, height as usize);
+    let (mut size, mut error) = (0, 0);
+    for n in 0..5 {
+        let mut buf = common::frame(width, height, n);
+        buf.resize(w * h * 3, 0);
+        for y in 0..h {
+            for x in 0..w {
+                let check = (x + n as usize) / 4 % 2 == y / 4 % 2;
+                buf[w * h + y * w + x] = if check { 60 } else { 190 };
+                buf[w * h * 2 + y * w + x] = if x % 8 < 3 { 90 } else { 170 };
+            }
+        }
+        let image = Image::new(
+            Colorspace::I444,
+            width,
+            height,
+            &[
+                Plane { stride: width, data: &buf[..w * h] },
+                Plane { stride: width, data: &buf[w * h..w * h * 2] },
+                Plane { stride: width, data: &buf[w * h * 2..] },
+            ],
+        );
+        size += encoder.encode(n as i64, image).unwrap().0.entirety().len();
+
+        let recon = encoder.reconstructed().unwrap();
+        for i in 1..3 {
+            let (plane, stride) = (recon.plane(i), recon.stride(i) as usize);
+            for y in 0..h {
+                for x in 0..w {
+                    let source = buf[w * h * i + y * w + x] as i64;
+                    let diff = source - plane[y * stride + x] as i64;
+                    error += (diff * diff) as u64;
+                }
+            }
+        }
+    }
+    (offset, size, error)
+}
+
+#[test]
+fn auto_chroma_qp_sharpens_444() {
+    let (plain_offset, plain_size, plain_error) = sharp_chroma(false);
+    let (auto_offset, auto_size, auto_error) = sharp_chroma(true);
+    assert_eq!(auto_offset, plain_offset - 6);
+    assert!(auto_size > plain_size);
+    assert!(auto_error < plain_error);
+
+    // 4:2:2 is quantized like 4:2:0, so it's left alone.
+    let offset = |auto: bool| {
+        let mut setup =
+            Setup::preset(Preset::Medium, Tune::None, false, true);
+        if auto {
+            setup = setup.auto_chroma_qp();
+        }
+        let encoder = setup
+            .build(Colorspace::I422, common::WIDTH, common::HEIGHT)
+            .unwrap();
+        encoder.effective_params().raw().analyse.i_chroma_qp_offset
+    };
+    assert_eq!(offset(true), offset(false));
+}
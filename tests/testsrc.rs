@@ -0,0 +1,50 @@
+#![cfg(feature = "testsrc")]
+
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+#[test]
+fn gradients_encode() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let mut encoder = common::fast()
+        .build(Colorspace::I420, width, height)
+        .unwrap();
+
+    for n in 0..10 {
+        let image = testsrc::gradient(Colorspace::I420, width, height, n);
+        let (data, picture) = encoder.encode(n as i64, image.as_image())
+            .unwrap();
+        assert_eq!(common::slices(&data), 1);
+        assert_eq!(picture.pts(), n as i64);
+    }
+
+    // The ramp goes from black to white, and scrolls by 4 pixels a frame.
+    let mut first = testsrc::gradient(Colorspace::I420, width, height, 0);
+    let mut second = testsrc::gradient(Colorspace::I420, width, height, 1);
+    let first = first.plane_mut(0);
+    assert_eq!((first[0], first[width as usize - 1]), (16, 235));
+    assert_eq!(second.plane_mut(0)[0], first[4]);
+}
+
+#[test]
+fn patterns_encode_in_every_colorspace() {
+    use Colorspace::*;
+
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    for &csp in &[I400, I420, NV12, NV21, I422, NV16, I444, YV24, BGR, RGB] {
+        let mut encoder = common::fast().build(csp, width, height).unwrap();
+        let solid = testsrc::solid(csp, width, height, [81, 90, 240]);
+        let bars = testsrc::bars(csp, width, height, 3);
+
+        assert!(encoder.encode(0, solid.as_image()).is_ok(), "{:?}", csp);
+        assert!(encoder.encode(1, bars.as_image()).is_ok(), "{:?}", csp);
+    }
+
+    // BT.601 red.
+    let mut red = testsrc::solid(RGB, 4, 2, [81, 90, 240]);
+    let pixel = &red.plane_mut(0)[..3];
+    assert!(pixel[0] > 250 && pixel[1] < 5 && pixel[2] < 5);
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "std")]
+
+extern crate x264;
+
+mod common;
+
+use x264::*;
+
+/// The types of the units in an Annex B stream.
+fn unit_types(stream: &[u8]) -> Vec<u8> {
+    stream
+        .windows(4)
+        .filter(|window| window[..3] == [0, 0, 1])
+        .map(|window| window[3] & 0x1F)
+        .collect()
+}
+
+#[test]
+fn transcodes_a_playable_stream() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let build = || {
+        Setup::preset(Preset::Medium, Tune::None, false, false)
+            .threads(1)
+            .fps(30, 1)
+            .build(Colorspace::I420, width, height)
+            .unwrap()
+    };
+
+    let mut transcoder = Transcoder::new(build(), Vec::new());
+    let mut timestamps = Vec::new();
+    for n in 0..30 {
+        let buf = common::frame(width, height, n);
+        let image = common::image(&buf, width, height);
+        if let Some(written) = transcoder.push(image).unwrap() {
+            timestamps.push(written);
+        }
+    }
+    assert_eq!(transcoder.frames_written(), timestamps.len() as u64);
+    let stream = transcoder.finish().unwrap();
+
+    assert_eq!(stream[..5], [0, 0, 0, 1, 0x67]);
+    let types = unit_types(&stream);
+    let slices = types.iter().filter(|&&kind| kind == 1 || kind == 5);
+    assert_eq!(slices.count(), 30);
+    assert!(timestamps.iter().all(|&(pts, dts)| dts >= 0 && dts <= pts));
+
+    // The timebase is the framerate's, so the timestamps are the frame
+    // numbers, as with `common::encode`.
+    let mut expected = Vec::new();
+    common::encode(build(), 30, |data, _| {
+        expected.extend_from_slice(data.entirety());
+    });
+    assert_eq!(stream, expected);
+}
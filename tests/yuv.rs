@@ -0,0 +1,51 @@
+#![cfg(feature = "yuv")]
+
+extern crate x264;
+extern crate yuv;
+
+mod common;
+
+use x264::*;
+
+#[test]
+fn yuv_frames_keep_their_strides() {
+    let (width, height) = (common::WIDTH, common::HEIGHT);
+    let (w, h) = (width as usize, height as usize);
+
+    // Pad every row, with junk that mustn't end up in the picture.
+    let tight = common::frame(width, height, 0);
+    let pad = |plane: &[u8], w: usize, stride: usize| {
+        let mut out = Vec::new();
+        for row in plane.chunks(w) {
+            out.extend_from_slice(row);
+            out.resize(out.len() + stride - w, 0xEE);
+        }
+        out
+    };
+    let luma = w * h;
+    let y = pad(&tight[..luma], w, w + 16);
+    let u = pad(&tight[luma..luma * 5 / 4], w / 2, w / 2 + 8);
+    let v = pad(&tight[luma * 5 / 4..], w / 2, w / 2 + 8);
+
+    let frame = yuv::YuvPlanarImage {
+        y_plane: &y,
+        y_stride: (w + 16) as u32,
+        u_plane: &u,
+        u_stride: (w / 2 + 8) as u32,
+        v_plane: &v,
+        v_stride: (w / 2 + 8) as u32,
+        width: width as u32,
+        height: height as u32,
+    };
+    let image = Image::from_yuv_planes(Colorspace::I420, &frame);
+    assert_eq!(&image.raw().i_stride[..3], &[80, 40, 40]);
+
+    let encode = |image| {
+        let mut encoder = common::fast()
+            .build(Colorspace::I420, width, height)
+            .unwrap();
+        let (data, _) = encoder.encode(0, image).unwrap();
+        data.entirety().to_vec()
+    };
+    assert_eq!(encode(image), encode(common::image(&tight, width, height)));
+}